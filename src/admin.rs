@@ -0,0 +1,193 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A group of administrator commands that can be granted to a role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdminPermission {
+    /// Muting and unmuting players and the chat.
+    Mute,
+    /// Warning players.
+    Warn,
+    /// Kicking players.
+    Kick,
+    /// Banning players and clearing bans.
+    Ban,
+    /// Controlling the game, like faceoffs, the clock, the score and pausing.
+    Game,
+    /// Changing game rules, like icing, offside and team size.
+    Rules,
+    /// Server settings, like allowing joins, recordings and restarting the server.
+    Server,
+}
+
+impl AdminPermission {
+    pub const ALL: [AdminPermission; 7] = [
+        AdminPermission::Mute,
+        AdminPermission::Warn,
+        AdminPermission::Kick,
+        AdminPermission::Ban,
+        AdminPermission::Game,
+        AdminPermission::Rules,
+        AdminPermission::Server,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+impl Display for AdminPermission {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            AdminPermission::Mute => "mute",
+            AdminPermission::Warn => "warn",
+            AdminPermission::Kick => "kick",
+            AdminPermission::Ban => "ban",
+            AdminPermission::Game => "game",
+            AdminPermission::Rules => "rules",
+            AdminPermission::Server => "server",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for AdminPermission {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AdminPermission::ALL
+            .into_iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// A set of [AdminPermission] values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdminPermissions(u32);
+
+impl AdminPermissions {
+    pub fn none() -> Self {
+        Self(0)
+    }
+
+    pub fn all() -> Self {
+        AdminPermission::ALL.into_iter().collect()
+    }
+
+    pub fn contains(&self, permission: AdminPermission) -> bool {
+        self.0 & permission.bit() != 0
+    }
+
+    pub fn insert(&mut self, permission: AdminPermission) {
+        self.0 |= permission.bit();
+    }
+}
+
+impl FromIterator<AdminPermission> for AdminPermissions {
+    fn from_iter<T: IntoIterator<Item = AdminPermission>>(iter: T) -> Self {
+        let mut res = Self::none();
+        for permission in iter {
+            res.insert(permission);
+        }
+        res
+    }
+}
+
+impl FromStr for AdminPermissions {
+    type Err = String;
+
+    /// Parses a comma-separated list of permissions. "all" grants every permission.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::none();
+        for part in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            if part.eq_ignore_ascii_case("all") {
+                res = Self::all();
+            } else {
+                let permission = part
+                    .parse::<AdminPermission>()
+                    .map_err(|_| format!("unknown permission {}", part))?;
+                res.insert(permission);
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// A named set of permissions.
+#[derive(Debug, Clone)]
+pub struct AdminRole {
+    pub name: String,
+    pub permissions: AdminPermissions,
+}
+
+impl AdminRole {
+    /// The roles that exist unless they are overridden in the configuration.
+    pub fn default_roles() -> Vec<AdminRole> {
+        vec![
+            AdminRole {
+                name: "moderator".to_owned(),
+                permissions: [
+                    AdminPermission::Mute,
+                    AdminPermission::Warn,
+                    AdminPermission::Kick,
+                ]
+                .into_iter()
+                .collect(),
+            },
+            AdminRole {
+                name: "referee".to_owned(),
+                permissions: [AdminPermission::Game].into_iter().collect(),
+            },
+            AdminRole {
+                name: "owner".to_owned(),
+                permissions: AdminPermissions::all(),
+            },
+        ]
+    }
+}
+
+/// A named administrator account with its own password.
+#[derive(Debug, Clone)]
+pub struct AdminAccount {
+    pub name: String,
+    pub password: String,
+    pub role: AdminRole,
+}
+
+/// The administrator status of a logged in player.
+#[derive(Debug, Clone)]
+pub struct AdminLogin {
+    /// Account name, or [None] if the shared administrator password was used.
+    pub account: Option<String>,
+    pub role: String,
+    pub permissions: AdminPermissions,
+}
+
+impl AdminLogin {
+    pub(crate) fn full_access(role: &str) -> Self {
+        Self {
+            account: None,
+            role: role.to_owned(),
+            permissions: AdminPermissions::all(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::{AdminPermission, AdminPermissions};
+
+    #[test]
+    fn parse_permissions() {
+        let p = "mute, kick".parse::<AdminPermissions>().unwrap();
+        assert!(p.contains(AdminPermission::Mute));
+        assert!(p.contains(AdminPermission::Kick));
+        assert!(!p.contains(AdminPermission::Ban));
+
+        let p = "all".parse::<AdminPermissions>().unwrap();
+        assert_eq!(p, AdminPermissions::all());
+
+        assert!("mute,restart".parse::<AdminPermissions>().is_err());
+    }
+}
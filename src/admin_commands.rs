@@ -1,18 +1,31 @@
-use crate::server::{HQMServer, MuteStatus, PlayerListExt, ServerPlayerData};
+use crate::admin::{AdminLogin, AdminPermission};
+use crate::ban::{format_ban_duration, BanEntry, BanInfo};
+use crate::server::{
+    HQMServer, MuteStatus, PlayerListExt, ServerPlayerData, TeamRequest, TeamRequestSource,
+};
 
-use crate::game::{PlayerId, PlayerIndex};
+use crate::game::{PhysicsConfiguration, PlayerId, PlayerIndex, Rink, Team};
+use crate::gamemode::match_util::{get_faceoff_spot, RinkFaceoffSpot};
 use crate::gamemode::{ExitReason, GameMode};
+use crate::names::NamePattern;
 use crate::ReplayRecording;
+use ini::Ini;
+use ipnet::IpNet;
+use nalgebra::{Point3, Vector3};
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tracing::info;
 
 impl HQMServer {
     pub(crate) fn set_allow_join(&mut self, admin_player_id: PlayerId, allowed: bool) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        let command = if allowed { "enablejoin" } else { "disablejoin" };
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Server,
+            command,
+        ) {
             self.allow_join = allowed;
 
             if allowed {
@@ -27,6 +40,7 @@ impl HQMServer {
                 let msg = format!("Joins disabled by {}", player.player_name);
                 self.state.players.add_server_chat_message(msg);
             }
+            self.audit(admin_player_id, command, "", None, Ok(()));
         }
     }
 
@@ -34,13 +48,13 @@ impl HQMServer {
         &mut self,
         admin_player_id: PlayerId,
         mute_player_index: PlayerIndex,
+        duration: Option<Duration>,
     ) {
-        if let Some(admin_player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Mute,
+            "mute",
+        ) {
             let admin_player_name = admin_player.player_name.clone();
 
             if let Some((mute_player_id, mute_player)) = self
@@ -49,28 +63,53 @@ impl HQMServer {
                 .players
                 .get_player_mut_by_index(mute_player_index)
             {
-                mute_player.is_muted = MuteStatus::Muted;
+                let mute_player_name = mute_player.player_name.clone();
+                let args = duration.map_or(String::new(), |x| (x.as_secs() / 60).to_string());
+                self.audit(admin_player_id, "mute", &args, Some(mute_player_id), Ok(()));
                 info!(
                     "{} ({}) muted {} ({})",
-                    admin_player_name, admin_player_id, mute_player.player_name, mute_player_id
+                    admin_player_name, admin_player_id, mute_player_name, mute_player_id
                 );
-                let msg = format!("{} muted by {}", mute_player.player_name, admin_player_name);
-                self.state.players.add_server_chat_message(msg);
+                self.mute_player_by(mute_player_id, &admin_player_name, duration);
             }
         }
     }
 
+    /// Mutes a player.
+    ///
+    /// If a duration is given, the mute is lifted automatically when it has passed.
+    pub(crate) fn mute_player_by(
+        &mut self,
+        mute_player_id: PlayerId,
+        muted_by: &str,
+        duration: Option<Duration>,
+    ) {
+        if let Some(mute_player) = self.state.players.players.get_player_mut(mute_player_id) {
+            mute_player.is_muted = MuteStatus::Muted;
+            mute_player.mute_expires = duration.map(|x| Instant::now() + x);
+            let msg = match duration {
+                Some(duration) => format!(
+                    "{} muted by {} for {} minutes",
+                    mute_player.player_name,
+                    muted_by,
+                    duration.as_secs() / 60
+                ),
+                None => format!("{} muted by {}", mute_player.player_name, muted_by),
+            };
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
     pub(crate) fn unmute_player(
         &mut self,
         admin_player_id: PlayerId,
         mute_player_index: PlayerIndex,
     ) {
-        if let Some(admin_player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Mute,
+            "unmute",
+        ) {
             let admin_player_name = admin_player.player_name.clone();
 
             if let Some((mute_player_id, mute_player)) = self
@@ -81,14 +120,14 @@ impl HQMServer {
             {
                 let old_status = mute_player.is_muted;
                 mute_player.is_muted = MuteStatus::NotMuted;
+                mute_player.mute_expires = None;
+                let mute_player_name = mute_player.player_name.clone();
+                self.audit(admin_player_id, "unmute", "", Some(mute_player_id), Ok(()));
                 info!(
                     "{} ({}) unmuted {} ({})",
-                    admin_player_name, admin_player_id, mute_player.player_name, mute_player_id
-                );
-                let msg = format!(
-                    "{} unmuted by {}",
-                    mute_player.player_name, admin_player_name
+                    admin_player_name, admin_player_id, mute_player_name, mute_player_id
                 );
+                let msg = format!("{} unmuted by {}", mute_player_name, admin_player_name);
                 if old_status == MuteStatus::Muted {
                     self.state.players.add_server_chat_message(msg);
                 } else {
@@ -100,18 +139,18 @@ impl HQMServer {
         }
     }
 
-    #[allow(dead_code)]
+    /// Mutes a player without telling them, so their messages are only shown to themselves.
     pub(crate) fn shadowmute_player(
         &mut self,
         admin_player_id: PlayerId,
         mute_player_index: PlayerIndex,
+        duration: Option<Duration>,
     ) {
-        if let Some(admin_player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Mute,
+            "shadowmute",
+        ) {
             let admin_player_name = admin_player.player_name.clone();
 
             if let Some((mute_player_id, mute_player)) = self
@@ -122,20 +161,24 @@ impl HQMServer {
             {
                 let old_status = mute_player.is_muted;
                 mute_player.is_muted = MuteStatus::ShadowMuted;
+                mute_player.mute_expires = duration.map(|x| Instant::now() + x);
+                let mute_player_name = mute_player.player_name.clone();
+                let args = duration.map_or(String::new(), |x| (x.as_secs() / 60).to_string());
+                self.audit(
+                    admin_player_id,
+                    "shadowmute",
+                    &args,
+                    Some(mute_player_id),
+                    Ok(()),
+                );
                 info!(
                     "{} ({}) shadowmuted {} ({})",
-                    admin_player_name, admin_player_id, mute_player.player_name, mute_player_id
-                );
-                let msg = format!(
-                    "{} shadowmuted by {}",
-                    mute_player.player_name, admin_player_name
+                    admin_player_name, admin_player_id, mute_player_name, mute_player_id
                 );
+                let msg = format!("{} shadowmuted by {}", mute_player_name, admin_player_name);
                 if old_status == MuteStatus::Muted {
                     // Fake "unmuting" message
-                    let msg = format!(
-                        "{} unmuted by {}",
-                        mute_player.player_name, admin_player_name
-                    );
+                    let msg = format!("{} unmuted by {}", mute_player_name, admin_player_name);
                     self.state
                         .players
                         .add_directed_server_chat_message(msg, mute_player_id);
@@ -147,56 +190,100 @@ impl HQMServer {
         }
     }
 
+    /// Lifts timed mutes that have expired. Called every tick.
+    pub(crate) fn update_mutes(&mut self) {
+        let now = Instant::now();
+        let mut messages = vec![];
+        for (player_id, player) in self.state.players.players.iter_players_mut() {
+            if let Some(old_status) = player.lift_expired_mute(now) {
+                info!("{} ({}) mute expired", player.player_name, player_id);
+                // A shadowmuted player never knew about the mute
+                if old_status == MuteStatus::Muted {
+                    messages.push(format!("{} is no longer muted", player.player_name));
+                }
+            }
+        }
+        for msg in messages {
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
     pub(crate) fn mute_chat(&mut self, admin_player_id: PlayerId) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Mute,
+            "mutechat",
+        ) {
             self.is_muted = true;
 
             let msg = format!("Chat muted by {}", player.player_name);
             info!("{} ({}) muted chat", player.player_name, admin_player_id);
             self.state.players.add_server_chat_message(msg);
+            self.audit(admin_player_id, "mutechat", "", None, Ok(()));
         }
     }
 
     pub(crate) fn unmute_chat(&mut self, admin_player_id: PlayerId) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Mute,
+            "unmutechat",
+        ) {
             self.is_muted = false;
 
             let msg = format!("Chat unmuted by {}", player.player_name);
             info!("{} ({}) unmuted chat", player.player_name, admin_player_id);
 
             self.state.players.add_server_chat_message(msg);
+            self.audit(admin_player_id, "unmutechat", "", None, Ok(()));
         }
     }
 
-    pub(crate) fn admin_login(&mut self, player_id: PlayerId, password: &str) {
+    pub(crate) fn admin_login(&mut self, player_id: PlayerId, arg: &str) {
+        // Named accounts are logged in with "/admin <account> <password>",
+        // while "/admin <password>" uses the shared administrator password
+        let account_login = arg.split_once(' ').and_then(|(account_name, password)| {
+            self.config
+                .admins
+                .iter()
+                .find(|x| x.name == account_name && x.password == password)
+                .map(|account| AdminLogin {
+                    account: Some(account.name.clone()),
+                    role: account.role.name.clone(),
+                    permissions: account.role.permissions,
+                })
+        });
+        let login = account_login.or_else(|| {
+            if self.config.password.as_deref().is_some_and(|x| x == arg) {
+                Some(AdminLogin::full_access("owner"))
+            } else {
+                None
+            }
+        });
         if let Some(player) = self.state.players.players.get_player_mut(player_id) {
-            let msg = if player.is_admin {
-                "You are already logged in as administrator"
-            } else if self
-                .config
-                .password
-                .as_deref()
-                .is_some_and(|x| x == password)
-            {
-                player.is_admin = true;
-                info!("{} ({}) is now admin", player.player_name, player_id);
-                "Successfully logged in as administrator"
+            let msg = if player.is_admin() {
+                "You are already logged in as administrator".to_owned()
+            } else if let Some(login) = login {
+                let msg = if let Some(account) = login.account.as_deref() {
+                    info!(
+                        "{} ({}) is now admin with account {} ({})",
+                        player.player_name, player_id, account, login.role
+                    );
+                    format!("Successfully logged in as {} ({})", account, login.role)
+                } else {
+                    info!("{} ({}) is now admin", player.player_name, player_id);
+                    "Successfully logged in as administrator".to_owned()
+                };
+                player.admin = Some(login);
+                self.audit(player_id, "admin", "", None, Ok(()));
+                msg
             } else {
                 info!(
                     "{} ({}) tried to become admin, entered wrong password",
                     player.player_name, player_id
                 );
-                "Wrong administrator password"
+                self.audit(player_id, "admin", "", None, Err("wrong password"));
+                "Wrong administrator password".to_owned()
             };
             self.state
                 .players
@@ -204,26 +291,6 @@ impl HQMServer {
         }
     }
 
-    pub(crate) fn restart_server(&mut self, admin_player_id: PlayerId) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
-            if let Some(server_service) = self.config.server_service.as_deref() {
-                let msg = format!("{} started server restart", player.player_name);
-                self.state.players.add_server_chat_message(msg);
-                let ctl = systemctl::SystemCtl::default();
-                if let Err(_) = ctl.restart(server_service) {
-                    self.state
-                        .players
-                        .add_directed_server_chat_message("Restart failed", admin_player_id);
-                }
-            }
-        }
-    }
-
     pub(crate) fn kick_all_matching<B: GameMode>(
         &mut self,
         admin_player_id: PlayerId,
@@ -231,43 +298,24 @@ impl HQMServer {
         ban_player: bool,
         behaviour: &mut B,
     ) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        let command = if ban_player { "banall" } else { "kickall" };
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            if ban_player {
+                AdminPermission::Ban
+            } else {
+                AdminPermission::Kick
+            },
+            command,
+        ) {
             let admin_player_name = player.player_name.clone();
 
-            enum Matching<'a> {
-                StartsWith(&'a str),
-                EndsWith(&'a str),
-                Contains(&'a str),
-                Equals(&'a str),
-            }
-
-            impl<'a> Matching<'a> {
-                fn is_matching(&self, player_name: &str) -> bool {
-                    match self {
-                        Matching::StartsWith(s) => player_name.starts_with(s),
-                        Matching::EndsWith(s) => player_name.ends_with(s),
-                        Matching::Contains(s) => player_name.contains(s),
-                        Matching::Equals(s) => player_name == *s,
-                    }
-                }
-            }
-
-            // 0 full string | 1 begins with | 2 ends with | 3 contains
-            let matching = if kick_player_name.starts_with("%") {
-                if kick_player_name.ends_with("%") {
-                    Matching::Contains(&kick_player_name[1..kick_player_name.len() - 1])
-                } else {
-                    Matching::StartsWith(&kick_player_name[1..kick_player_name.len()])
-                }
-            } else if kick_player_name.ends_with("%") {
-                Matching::EndsWith(&kick_player_name[0..kick_player_name.len() - 1])
-            } else {
-                Matching::Equals(&kick_player_name)
+            let Some(matching) = NamePattern::parse(kick_player_name) else {
+                let msg = format!("Invalid name pattern {}", kick_player_name);
+                self.state
+                    .players
+                    .add_directed_server_chat_message(msg, admin_player_id);
+                return;
             };
 
             let kick_player_list: Vec<_> = self
@@ -288,6 +336,13 @@ impl HQMServer {
             if !kick_player_list.is_empty() {
                 for (player_id, player_name, player_addr) in kick_player_list {
                     if player_id != admin_player_id {
+                        self.audit(
+                            admin_player_id,
+                            command,
+                            kick_player_name,
+                            Some(player_id),
+                            Ok(()),
+                        );
                         behaviour.before_player_exit(
                             self.into(),
                             player_id,
@@ -296,7 +351,10 @@ impl HQMServer {
                         self.remove_player(player_id, true);
 
                         if ban_player {
-                            self.ban.ban_ip(player_addr.ip());
+                            self.ban.ban_ip(
+                                player_addr.ip(),
+                                BanInfo::new(Some(&player_name), &admin_player_name, "", None),
+                            );
 
                             info!(
                                 "{} ({}) banned {} ({})",
@@ -313,6 +371,13 @@ impl HQMServer {
                             self.state.players.add_server_chat_message(msg);
                         }
                     } else {
+                        self.audit(
+                            admin_player_id,
+                            command,
+                            kick_player_name,
+                            Some(player_id),
+                            Err("cannot kick self"),
+                        );
                         if ban_player {
                             self.state.players.add_directed_server_chat_message(
                                 "You cannot ban yourself",
@@ -327,36 +392,17 @@ impl HQMServer {
                     }
                 }
             } else {
-                match matching {
-                    Matching::Equals(_) => {
-                        // full string
-                        let msg = format!("No player names match {}", kick_player_name);
-                        self.state
-                            .players
-                            .add_directed_server_chat_message(msg, admin_player_id);
-                    }
-                    Matching::StartsWith(_) => {
-                        // begins with%
-                        let msg = format!("No player names begin with {}", kick_player_name);
-                        self.state
-                            .players
-                            .add_directed_server_chat_message(msg, admin_player_id);
-                    }
-                    Matching::EndsWith(_) => {
-                        // %ends with
-                        let msg = format!("No player names end with {}", kick_player_name);
-                        self.state
-                            .players
-                            .add_directed_server_chat_message(msg, admin_player_id);
-                    }
-                    Matching::Contains(_) => {
-                        // %contains%
-                        let msg = format!("No player names contain {}", kick_player_name);
-                        self.state
-                            .players
-                            .add_directed_server_chat_message(msg, admin_player_id);
-                    }
-                }
+                self.audit(
+                    admin_player_id,
+                    command,
+                    kick_player_name,
+                    None,
+                    Err("no matching players"),
+                );
+                let msg = format!("No player names match {}", kick_player_name);
+                self.state
+                    .players
+                    .add_directed_server_chat_message(msg, admin_player_id);
             }
         }
     }
@@ -366,14 +412,20 @@ impl HQMServer {
         admin_player_id: PlayerId,
         kick_player_index: PlayerIndex,
         ban_player: bool,
+        ban_duration: Option<Duration>,
+        ban_reason: &str,
         behaviour: &mut B,
     ) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
-        {
+        let command = if ban_player { "ban" } else { "kick" };
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            if ban_player {
+                AdminPermission::Ban
+            } else {
+                AdminPermission::Kick
+            },
+            command,
+        ) {
             let admin_player_name = player.player_name.clone();
 
             if kick_player_index != admin_player_id.index {
@@ -383,30 +435,36 @@ impl HQMServer {
                     .players
                     .get_player_by_index(kick_player_index)
                 {
-                    if let ServerPlayerData::NetworkPlayer { data } = &kick_player.data {
+                    if let ServerPlayerData::NetworkPlayer { .. } = &kick_player.data {
                         let kick_player_name = kick_player.player_name.clone();
-                        let kick_ip = data.addr.ip().clone();
-                        behaviour.before_player_exit(
-                            self.into(),
-                            kick_player_id,
-                            ExitReason::AdminKicked,
-                        );
-                        self.remove_player(kick_player_id, true);
-
-                        if ban_player {
-                            self.ban.ban_ip(kick_ip);
-
+                        let ban = if ban_player {
+                            let duration =
+                                ban_duration.map_or("permanent".to_owned(), format_ban_duration);
+                            let args = format!("{} {}", duration, ban_reason);
+                            self.audit(
+                                admin_player_id,
+                                command,
+                                args.trim(),
+                                Some(kick_player_id),
+                                Ok(()),
+                            );
                             info!(
-                                "{} ({}) banned {} ({})",
+                                "{} ({}) banned {} ({}), {}: {}",
                                 admin_player_name,
                                 admin_player_id,
                                 kick_player_name,
-                                kick_player_id
+                                kick_player_id,
+                                duration,
+                                ban_reason
                             );
-                            let msg =
-                                format!("{} banned by {}", kick_player_name, admin_player_name);
-                            self.state.players.add_server_chat_message(msg);
+                            Some(BanInfo::new(
+                                None,
+                                &admin_player_name,
+                                ban_reason,
+                                ban_duration,
+                            ))
                         } else {
+                            self.audit(admin_player_id, command, "", Some(kick_player_id), Ok(()));
                             info!(
                                 "{} ({}) kicked {} ({})",
                                 admin_player_name,
@@ -414,13 +472,25 @@ impl HQMServer {
                                 kick_player_name,
                                 kick_player_id
                             );
-                            let msg =
-                                format!("{} kicked by {}", kick_player_name, admin_player_name);
-                            self.state.players.add_server_chat_message(msg);
-                        }
+                            None
+                        };
+                        self.kick_player_by(
+                            kick_player_id,
+                            ban,
+                            &admin_player_name,
+                            ExitReason::AdminKicked,
+                            behaviour,
+                        );
                     }
                 }
             } else {
+                self.audit(
+                    admin_player_id,
+                    command,
+                    "",
+                    Some(admin_player_id),
+                    Err("cannot kick self"),
+                );
                 if ban_player {
                     self.state.players.add_directed_server_chat_message(
                         "You cannot ban yourself",
@@ -436,28 +506,286 @@ impl HQMServer {
         }
     }
 
-    pub(crate) fn clear_bans(&mut self, admin_player_id: PlayerId) {
-        if let Some(player) = self
-            .state
-            .players
-            .players
-            .check_admin_or_deny(admin_player_id)
+    /// Kicks a player, and bans their IP address if `ban` is set.
+    ///
+    /// Kicks by administrators, player votes and automatic actions like warnings all go through here.
+    pub(crate) fn kick_player_by<B: GameMode>(
+        &mut self,
+        kick_player_id: PlayerId,
+        ban: Option<BanInfo>,
+        kicked_by: &str,
+        reason: ExitReason,
+        behaviour: &mut B,
+    ) {
+        if let Some((kick_player_name, kick_ip)) =
+            self.remove_kicked_player(kick_player_id, reason, behaviour)
         {
+            let msg = if let Some(mut ban) = ban {
+                ban.player_name = Some(kick_player_name.to_string());
+                let msg = ban_message(&kick_player_name, kicked_by, &ban);
+                self.ban.ban_ip(kick_ip, ban);
+                msg
+            } else {
+                format!("{} kicked by {}", kick_player_name, kicked_by)
+            };
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
+    /// Removes a network player from the server and returns their name and address.
+    fn remove_kicked_player<B: GameMode>(
+        &mut self,
+        kick_player_id: PlayerId,
+        reason: ExitReason,
+        behaviour: &mut B,
+    ) -> Option<(Rc<str>, IpAddr)> {
+        let kick_player = self.state.players.players.get_player(kick_player_id)?;
+        let ServerPlayerData::NetworkPlayer { data } = &kick_player.data else {
+            return None;
+        };
+        let kick_player_name = kick_player.player_name.clone();
+        let kick_ip = data.addr.ip();
+        behaviour.before_player_exit(self.into(), kick_player_id, reason);
+        self.remove_player(kick_player_id, true);
+        Some((kick_player_name, kick_ip))
+    }
+
+    /// Bans a CIDR range, or the range with the given prefix length around a player's address,
+    /// and kicks all players in it.
+    pub(crate) fn ban_range<B: GameMode>(
+        &mut self,
+        admin_player_id: PlayerId,
+        arg: &str,
+        behaviour: &mut B,
+    ) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Ban,
+            "banrange",
+        ) {
+            let admin_player_name = player.player_name.clone();
+
+            let range = if let Ok(range) = arg.parse::<IpNet>() {
+                Some(range)
+            } else if let Some((player_index, prefix_len)) = arg.split_once(' ') {
+                let ip = player_index
+                    .parse::<PlayerIndex>()
+                    .ok()
+                    .and_then(|index| self.state.players.players.get_player_by_index(index))
+                    .and_then(|(_, player)| player.ip());
+                match (ip, prefix_len.trim().parse::<u8>()) {
+                    (Some(ip), Ok(prefix_len)) => IpNet::new(ip, prefix_len).ok(),
+                    _ => None,
+                }
+            } else {
+                None
+            };
+            let Some(range) = range.map(|range| range.trunc()) else {
+                self.state.players.add_directed_server_chat_message(
+                    "Usage: /banrange <range> or /banrange <player> <prefix length>",
+                    admin_player_id,
+                );
+                return;
+            };
+            let min_prefix_len = match range {
+                IpNet::V4(_) => 16,
+                IpNet::V6(_) => 32,
+            };
+            if range.prefix_len() < min_prefix_len {
+                self.state
+                    .players
+                    .add_directed_server_chat_message("Range is too large", admin_player_id);
+                self.audit(
+                    admin_player_id,
+                    "banrange",
+                    arg,
+                    None,
+                    Err("range too large"),
+                );
+                return;
+            }
+
+            let ban = BanInfo::new(None, &admin_player_name, "", None);
+            self.ban.ban_range(range, ban.clone());
+            info!(
+                "{} ({}) banned range {}",
+                admin_player_name, admin_player_id, range
+            );
+            self.audit(
+                admin_player_id,
+                "banrange",
+                &range.to_string(),
+                None,
+                Ok(()),
+            );
+            let msg = format!("Range {} banned by {}", range, admin_player_name);
+            self.state.players.add_server_chat_message(msg);
+
+            let kick_player_list: Vec<_> = self
+                .state
+                .players
+                .players
+                .iter_players()
+                .filter(|(player_id, player)| {
+                    *player_id != admin_player_id
+                        && player.ip().is_some_and(|ip| range.contains(&ip))
+                })
+                .map(|(player_id, _)| player_id)
+                .collect();
+            for player_id in kick_player_list {
+                // The range ban already covers them, so their addresses are not banned again
+                if let Some((player_name, _)) =
+                    self.remove_kicked_player(player_id, ExitReason::AdminKicked, behaviour)
+                {
+                    let msg = ban_message(&player_name, &admin_player_name, &ban);
+                    self.state.players.add_server_chat_message(msg);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn clear_bans(&mut self, admin_player_id: PlayerId) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Ban,
+            "clearbans",
+        ) {
             self.ban.clear_all_bans();
             info!("{} ({}) cleared bans", player.player_name, admin_player_id);
 
             let msg = format!("Bans cleared by {}", player.player_name);
             self.state.players.add_server_chat_message(msg);
+            self.audit(admin_player_id, "clearbans", "", None, Ok(()));
         }
     }
 
-    pub fn set_recording(&mut self, admin_player_id: PlayerId, rule: &str) {
-        if let Some(player) = self
+    pub(crate) fn unban(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Ban,
+            "unban",
+        ) {
+            let admin_player_name = player.player_name.clone();
+            if arg.is_empty() {
+                self.state.players.add_directed_server_chat_message(
+                    "Usage: /unban <address, range or name>",
+                    admin_player_id,
+                );
+                return;
+            }
+            let removed = self.ban.unban(arg);
+            // Ranges and bans in read-only ban lists can still cover the address
+            let still_banned = arg
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| self.ban.is_known_banned(ip));
+            let msg = match removed.as_deref() {
+                None => {
+                    info!(
+                        "{} ({}) requested unban of {}",
+                        admin_player_name, admin_player_id, arg
+                    );
+                    self.audit(admin_player_id, "unban", arg, None, Ok(()));
+                    let msg = format!("Unban of {} requested", arg);
+                    self.state
+                        .players
+                        .add_directed_server_chat_message(msg, admin_player_id);
+                    return;
+                }
+                Some([]) => {
+                    let msg = if still_banned {
+                        format!(
+                            "{} is covered by another ban (range or read-only list)",
+                            arg
+                        )
+                    } else {
+                        format!("No ban found for {}", arg)
+                    };
+                    self.state
+                        .players
+                        .add_directed_server_chat_message(msg, admin_player_id);
+                    self.audit(admin_player_id, "unban", arg, None, Err("not banned"));
+                    return;
+                }
+                Some([entry]) => format!("{} unbanned by {}", ban_target(entry), admin_player_name),
+                Some(removed) => format!(
+                    "{} bans of {} removed by {}",
+                    removed.len(),
+                    arg,
+                    admin_player_name
+                ),
+            };
+            info!(
+                "{} ({}) removed bans of {}",
+                admin_player_name, admin_player_id, arg
+            );
+            self.audit(admin_player_id, "unban", arg, None, Ok(()));
+            self.state.players.add_server_chat_message(msg);
+            if still_banned {
+                let msg = format!(
+                    "{} is still covered by another ban (range or read-only list)",
+                    arg
+                );
+                self.state
+                    .players
+                    .add_directed_server_chat_message(msg, admin_player_id);
+            }
+        }
+    }
+
+    pub(crate) fn list_bans(&mut self, admin_player_id: PlayerId, page: usize) {
+        if self
             .state
             .players
             .players
-            .check_admin_or_deny(admin_player_id)
+            .check_permission_or_deny(admin_player_id, AdminPermission::Ban, "bans")
+            .is_none()
         {
+            return;
+        }
+        const PAGE_SIZE: usize = 5;
+        let msgs = match self.ban.list_bans() {
+            None => vec!["Loading the ban list, try again in a moment".to_owned()],
+            Some(bans) if bans.is_empty() => vec!["No bans".to_owned()],
+            Some(bans) => {
+                let pages = bans.len().div_ceil(PAGE_SIZE);
+                let page = page.clamp(1, pages);
+                let now = chrono::Utc::now();
+                std::iter::once(format!("Bans, page {} of {}:", page, pages))
+                    .chain(
+                        bans.iter()
+                            .skip((page - 1) * PAGE_SIZE)
+                            .take(PAGE_SIZE)
+                            .map(|entry| {
+                                let mut msg = ban_target(entry);
+                                match entry.info.expires.and_then(|x| (x - now).to_std().ok()) {
+                                    Some(left) => msg.push_str(&format!(
+                                        ", {} left",
+                                        format_ban_duration(round_duration(left))
+                                    )),
+                                    None => msg.push_str(", permanent"),
+                                }
+                                if let Some(reason) = &entry.info.reason {
+                                    msg.push_str(&format!(": {}", reason));
+                                }
+                                msg
+                            }),
+                    )
+                    .collect()
+            }
+        };
+        for msg in msgs {
+            self.state
+                .players
+                .add_directed_server_chat_message(msg, admin_player_id);
+        }
+    }
+
+    pub fn set_recording(&mut self, admin_player_id: PlayerId, rule: &str) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Server,
+            "replay",
+        ) {
             match rule {
                 "on" => {
                     self.config.recording_enabled = ReplayRecording::On;
@@ -469,6 +797,7 @@ impl HQMServer {
                     let msg = format!("Replays enabled by {}", player.player_name);
 
                     self.state.players.add_server_chat_message(msg);
+                    self.audit(admin_player_id, "replay", rule, None, Ok(()));
                 }
                 "off" => {
                     self.config.recording_enabled = ReplayRecording::Off;
@@ -480,6 +809,7 @@ impl HQMServer {
                     let msg = format!("Replays disabled by {}", player.player_name);
 
                     self.state.players.add_server_chat_message(msg);
+                    self.audit(admin_player_id, "replay", rule, None, Ok(()));
                 }
                 "standby" => {
                     self.config.recording_enabled = ReplayRecording::Standby;
@@ -491,9 +821,430 @@ impl HQMServer {
                     let msg = format!("Standby replay recording enabled by {}", player.player_name);
 
                     self.state.players.add_server_chat_message(msg);
+                    self.audit(admin_player_id, "replay", rule, None, Ok(()));
                 }
                 _ => {}
             }
         }
     }
+
+    pub(crate) fn physics_command(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Rules,
+            "physics",
+        ) {
+            let admin_player_name = player.player_name.clone();
+            let (property, value) = arg.split_once(' ').unwrap_or((arg, ""));
+            let value = value.trim();
+            if property.is_empty() {
+                let values: Vec<String> = self
+                    .physics_config
+                    .values()
+                    .into_iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                for msg in values.chunks(2) {
+                    self.state
+                        .players
+                        .add_directed_server_chat_message(msg.join(", "), admin_player_id);
+                }
+                return;
+            }
+
+            let res = if property == "preset" {
+                self.load_physics_preset(value)
+            } else {
+                self.physics_config.set(property, value)
+            };
+            match res {
+                Ok(()) => {
+                    let msg = if property == "preset" {
+                        info!(
+                            "{} ({}) loaded physics preset {}",
+                            admin_player_name, admin_player_id, value
+                        );
+                        format!("Physics preset {} loaded by {}", value, admin_player_name)
+                    } else {
+                        info!(
+                            "{} ({}) set physics {} to {}",
+                            admin_player_name, admin_player_id, property, value
+                        );
+                        format!(
+                            "Physics {} set to {} by {}",
+                            property, value, admin_player_name
+                        )
+                    };
+                    self.state.players.add_server_chat_message(msg);
+                    self.audit(admin_player_id, "physics", arg, None, Ok(()));
+                }
+                Err(e) => {
+                    self.audit(admin_player_id, "physics", arg, None, Err(&e));
+                    self.state
+                        .players
+                        .add_directed_server_chat_message(e, admin_player_id);
+                }
+            }
+        }
+    }
+
+    /// Loads a physics preset. "default" is built in, other presets are read from
+    /// files named *name*.ini in the physics preset directory, using the same format
+    /// as the [Physics] section of the configuration file.
+    fn load_physics_preset(&mut self, name: &str) -> Result<(), String> {
+        let mut physics_config = PhysicsConfiguration {
+            limit_jump_speed: self.physics_config.limit_jump_speed,
+            ..Default::default()
+        };
+        if name != "default" {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!("Invalid physics preset name {}", name));
+            }
+            let path = self
+                .config
+                .physics_preset_directory
+                .join(format!("{}.ini", name));
+            let preset = Ini::load_from_file(&path)
+                .map_err(|_| format!("Could not load physics preset {}", name))?;
+            let section = preset
+                .section(Some("Physics"))
+                .or_else(|| preset.section(None::<String>));
+            if let Some(section) = section {
+                for (property, value) in section.iter() {
+                    physics_config.set(property, value)?;
+                }
+            }
+        }
+        self.physics_config = physics_config;
+        Ok(())
+    }
+
+    /// Moves a skater to coordinates in meters, a faceoff spot or next to another player.
+    pub(crate) fn teleport_player(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Game,
+            "tp",
+        ) {
+            let admin_player_name = admin_player.player_name.clone();
+            let Some((player_index, target)) = arg.split_once(' ') else {
+                return;
+            };
+            let Ok(player_index) = player_index.parse::<PlayerIndex>() else {
+                return;
+            };
+            let target = target.trim();
+
+            let destination = match target.parse::<TeleportTarget>() {
+                Ok(TeleportTarget::Player(target_index)) => self
+                    .state
+                    .players
+                    .players
+                    .get_player_by_index(target_index)
+                    .and_then(|(_, player)| player.object.as_ref())
+                    .map(|(_, skater, _)| {
+                        // Next to the other player, facing the same way
+                        let pos = skater.body.pos + skater.body.rot * Vector3::new(1.0, 0.0, 0.0);
+                        (pos, Some(skater.body.rot))
+                    }),
+                Ok(TeleportTarget::FaceoffSpot(spot)) => {
+                    Some((faceoff_spot_position(&self.rink, spot), None))
+                }
+                Ok(TeleportTarget::Position(pos)) => Some((pos, None)),
+                Err(()) => None,
+            };
+            let rink = &self.rink;
+            let Some((pos, rot)) = destination.filter(|(pos, _)| is_inside_rink(rink, pos)) else {
+                self.state.players.add_directed_server_chat_message(
+                    "Unknown player, faceoff spot or position",
+                    admin_player_id,
+                );
+                return;
+            };
+
+            let Some((player_id, player)) = self
+                .state
+                .players
+                .players
+                .get_player_mut_by_index(player_index)
+            else {
+                return;
+            };
+            let Some((_, skater, team)) = &player.object else {
+                self.state
+                    .players
+                    .add_directed_server_chat_message("Player is not on the ice", admin_player_id);
+                return;
+            };
+            let rot = rot.unwrap_or(skater.body.rot);
+            let team = *team;
+            let player_name = player.player_name.clone();
+            // Respawning keeps the stick where it was relative to the skater
+            self.state
+                .players
+                .spawn_skater(player_id, team, pos, rot, true);
+
+            info!(
+                "{} ({}) teleported {} ({}) to {}",
+                admin_player_name, admin_player_id, player_name, player_id, target
+            );
+            self.audit(admin_player_id, "tp", target, Some(player_id), Ok(()));
+            let msg = format!("{} teleported to {}", player_name, target);
+            self.state
+                .players
+                .add_directed_server_chat_message(msg, admin_player_id);
+        }
+    }
+
+    /// Freezes or unfreezes a player, or all players if the argument is "all".
+    pub(crate) fn freeze_player(&mut self, admin_player_id: PlayerId, arg: &str, frozen: bool) {
+        let command = if frozen { "freeze" } else { "unfreeze" };
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Game,
+            command,
+        ) {
+            let admin_player_name = admin_player.player_name.clone();
+            let (verb, past) = if frozen {
+                ("frozen", "froze")
+            } else {
+                ("unfrozen", "unfroze")
+            };
+
+            if arg == "all" {
+                for (_, player) in self.state.players.players.iter_players_mut() {
+                    if let ServerPlayerData::NetworkPlayer { .. } = player.data {
+                        player.set_frozen(frozen);
+                    }
+                }
+                info!(
+                    "{} ({}) {} all players",
+                    admin_player_name, admin_player_id, past
+                );
+                self.audit(admin_player_id, command, arg, None, Ok(()));
+                let msg = format!("All players {} by {}", verb, admin_player_name);
+                self.state.players.add_server_chat_message(msg);
+            } else if let Ok(player_index) = arg.parse::<PlayerIndex>() {
+                if let Some((player_id, player)) = self
+                    .state
+                    .players
+                    .players
+                    .get_player_mut_by_index(player_index)
+                {
+                    player.set_frozen(frozen);
+                    let player_name = player.player_name.clone();
+                    info!(
+                        "{} ({}) {} {} ({})",
+                        admin_player_name, admin_player_id, past, player_name, player_id
+                    );
+                    self.audit(admin_player_id, command, "", Some(player_id), Ok(()));
+                    let msg = format!("{} {} by {}", player_name, verb, admin_player_name);
+                    self.state.players.add_server_chat_message(msg);
+                }
+            }
+        }
+    }
+
+    /// Moves a player to a team or to the spectators, even if teams are locked.
+    /// The move happens when the game mode next adds players, so team sizes still apply.
+    pub(crate) fn set_player_team(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Game,
+            "team",
+        ) {
+            let admin_player_name = admin_player.player_name.clone();
+            let Some((player_index, team)) = arg.split_once(' ') else {
+                return;
+            };
+            let Ok(player_index) = player_index.parse::<PlayerIndex>() else {
+                return;
+            };
+            let team = match team.trim() {
+                "red" => Some(Team::Red),
+                "blue" => Some(Team::Blue),
+                "spec" | "spectate" | "spectator" => None,
+                _ => {
+                    self.state.players.add_directed_server_chat_message(
+                        "Team must be red, blue or spec",
+                        admin_player_id,
+                    );
+                    return;
+                }
+            };
+            let Some((player_id, player)) = self
+                .state
+                .players
+                .players
+                .get_player_mut_by_index(player_index)
+            else {
+                return;
+            };
+            let current_team = player.object.as_ref().map(|(_, _, team)| *team);
+            if current_team == team {
+                player.team_request = None;
+                return;
+            }
+            player.team_request = Some(TeamRequest {
+                team,
+                source: TeamRequestSource::Admin {
+                    player_id: admin_player_id,
+                    player_name: admin_player_name,
+                },
+            });
+            self.audit(admin_player_id, "team", arg, Some(player_id), Ok(()));
+        }
+    }
+
+    pub(crate) fn lock_teams(&mut self, admin_player_id: PlayerId, locked: bool) {
+        let command = if locked { "lockteams" } else { "unlockteams" };
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Game,
+            command,
+        ) {
+            let admin_player_name = admin_player.player_name.clone();
+            self.state.players.teams_locked = locked;
+            let msg = if locked {
+                info!("{} ({}) locked teams", admin_player_name, admin_player_id);
+                format!("Teams locked by {}", admin_player_name)
+            } else {
+                info!("{} ({}) unlocked teams", admin_player_name, admin_player_id);
+                format!("Teams unlocked by {}", admin_player_name)
+            };
+            self.audit(admin_player_id, command, "", None, Ok(()));
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+}
+
+/// Chat message for a ban, like "Player banned by Admin for 2 hours: reason".
+fn ban_message(player_name: &str, banned_by: &str, ban: &BanInfo) -> String {
+    let mut msg = format!("{} banned by {}", player_name, banned_by);
+    let duration = ban
+        .expires
+        .zip(ban.created)
+        .and_then(|(expires, created)| (expires - created).to_std().ok());
+    if let Some(duration) = duration {
+        msg.push_str(&format!(" for {}", format_ban_duration(duration)));
+    }
+    if let Some(reason) = &ban.reason {
+        msg.push_str(&format!(": {}", reason));
+    }
+    msg
+}
+
+/// Describes a ban for chat messages, like "Troll (10.0.0.1)".
+fn ban_target(entry: &BanEntry) -> String {
+    match &entry.info.player_name {
+        Some(player_name) => format!("{} ({})", player_name, entry.target),
+        None => entry.target.clone(),
+    }
+}
+
+/// Rounds a duration up to whole units of the largest unit it contains, like 2 hours or 3 days.
+fn round_duration(duration: Duration) -> Duration {
+    let seconds = duration.as_secs();
+    let unit = [7 * 24 * 60 * 60, 24 * 60 * 60, 60 * 60, 60]
+        .into_iter()
+        .find(|unit| seconds >= *unit)
+        .unwrap_or(1);
+    Duration::from_secs(seconds.div_ceil(unit) * unit)
+}
+
+/// Height used for targets given without one, like faceoff spots.
+const TARGET_HEIGHT: f32 = 1.5;
+
+/// Where /tp moves a skater or /puck places a puck.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TeleportTarget {
+    Player(PlayerIndex),
+    FaceoffSpot(RinkFaceoffSpot),
+    /// Coordinates in meters, "x z" at skating height or "x y z".
+    Position(Point3<f32>),
+}
+
+impl FromStr for TeleportTarget {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(player_index) = s.parse::<PlayerIndex>() {
+            return Ok(TeleportTarget::Player(player_index));
+        }
+        if let Ok(spot) = s.parse::<RinkFaceoffSpot>() {
+            return Ok(TeleportTarget::FaceoffSpot(spot));
+        }
+        let coordinates: Option<Vec<f32>> = s
+            .split_whitespace()
+            .map(|x| x.parse::<f32>().ok().filter(|x| x.is_finite()))
+            .collect();
+        match coordinates.as_deref() {
+            Some(&[x, z]) => Ok(TeleportTarget::Position(Point3::new(x, TARGET_HEIGHT, z))),
+            Some(&[x, y, z]) => Ok(TeleportTarget::Position(Point3::new(x, y, z))),
+            _ => Err(()),
+        }
+    }
+}
+
+pub(crate) fn faceoff_spot_position(rink: &Rink, spot: RinkFaceoffSpot) -> Point3<f32> {
+    let center = get_faceoff_spot(rink, spot, 0.0, 0.0).center_position;
+    Point3::new(center.x, TARGET_HEIGHT, center.z)
+}
+
+pub(crate) fn is_inside_rink(rink: &Rink, pos: &Point3<f32>) -> bool {
+    (0.0..=rink.width).contains(&pos.x) && pos.y >= 0.0 && (0.0..=rink.length).contains(&pos.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin_commands::{is_inside_rink, TeleportTarget};
+    use crate::game::{PlayerIndex, Rink, Team};
+    use crate::gamemode::match_util::{RinkFaceoffSpot, RinkSide};
+    use nalgebra::Point3;
+
+    #[test]
+    fn teleport_target() {
+        assert_eq!(
+            "3".parse::<TeleportTarget>(),
+            Ok(TeleportTarget::Player(PlayerIndex(3)))
+        );
+        assert_eq!(
+            "center".parse::<TeleportTarget>(),
+            Ok(TeleportTarget::FaceoffSpot(RinkFaceoffSpot::Center))
+        );
+        assert_eq!(
+            "blue_zone_left".parse::<TeleportTarget>(),
+            Ok(TeleportTarget::FaceoffSpot(RinkFaceoffSpot::DefensiveZone(
+                Team::Blue,
+                RinkSide::LowerHalfZ
+            )))
+        );
+        assert_eq!(
+            "15 30".parse::<TeleportTarget>(),
+            Ok(TeleportTarget::Position(Point3::new(15.0, 1.5, 30.0)))
+        );
+        assert_eq!(
+            "15 2.5 30".parse::<TeleportTarget>(),
+            Ok(TeleportTarget::Position(Point3::new(15.0, 2.5, 30.0)))
+        );
+        assert_eq!("15.5".parse::<TeleportTarget>(), Err(()));
+        assert_eq!("1 2 3 4".parse::<TeleportTarget>(), Err(()));
+        assert_eq!("15 NaN".parse::<TeleportTarget>(), Err(()));
+        assert_eq!("15 inf".parse::<TeleportTarget>(), Err(()));
+        assert_eq!("red_zone".parse::<TeleportTarget>(), Err(()));
+    }
+
+    #[test]
+    fn teleport_inside_rink() {
+        let rink = Rink::new(30.0, 61.0, 8.5);
+        assert!(is_inside_rink(&rink, &Point3::new(15.0, 1.5, 30.5)));
+        assert!(is_inside_rink(&rink, &Point3::new(0.0, 0.0, 61.0)));
+        assert!(!is_inside_rink(&rink, &Point3::new(-1.0, 1.5, 30.0)));
+        assert!(!is_inside_rink(&rink, &Point3::new(15.0, 1.5, 62.0)));
+        assert!(!is_inside_rink(&rink, &Point3::new(15.0, -0.5, 30.0)));
+    }
 }
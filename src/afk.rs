@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use crate::server::{HQMServer, PlayerListExt, ServerPlayerData, TeamRequest, TeamRequestSource};
+
+/// Moves players on the ice who don't touch their controls to the spectators.
+///
+/// Only time while the game clock is running is counted. A value of 0 disables that step.
+#[derive(Debug, Clone)]
+pub struct AfkConfiguration {
+    pub warn_after: Duration,
+    pub spectate_after: Duration,
+}
+
+impl Default for AfkConfiguration {
+    fn default() -> Self {
+        Self {
+            warn_after: Duration::from_secs(60),
+            spectate_after: Duration::from_secs(90),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct AfkState {
+    last_time: u32,
+}
+
+impl AfkState {
+    /// Returns true if the game clock has changed since the last tick.
+    fn clock_running(&mut self, time: u32) -> bool {
+        let running = time != self.last_time;
+        self.last_time = time;
+        running
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AfkAction {
+    Warn,
+    Spectate,
+}
+
+fn to_ticks(duration: Duration) -> u32 {
+    (duration.as_millis() / 10) as u32
+}
+
+/// Counts one more AFK tick for a player and returns what should happen to them.
+fn advance_afk_ticks(
+    afk_ticks: &mut u32,
+    warn_ticks: u32,
+    spectate_ticks: u32,
+) -> Option<AfkAction> {
+    *afk_ticks += 1;
+    if spectate_ticks > 0 && *afk_ticks >= spectate_ticks {
+        *afk_ticks = 0;
+        Some(AfkAction::Spectate)
+    } else if warn_ticks > 0 && *afk_ticks == warn_ticks {
+        Some(AfkAction::Warn)
+    } else {
+        None
+    }
+}
+
+impl HQMServer {
+    /// Warns AFK players and asks the game mode to move them to the spectators. Called every tick.
+    pub(crate) fn update_afk_players(&mut self) {
+        let time = self.state.scoreboard.time;
+        if !self.afk.clock_running(time) || self.state.replay.is_in_replay() {
+            return;
+        }
+
+        let warn_ticks = to_ticks(self.config.afk.warn_after);
+        let spectate_ticks = to_ticks(self.config.afk.spectate_after);
+        let mut warned = smallvec::SmallVec::<[_; 8]>::new();
+        for (player_id, player) in self.state.players.players.iter_players_mut() {
+            if let ServerPlayerData::NetworkPlayer { data } = &mut player.data {
+                if player.object.is_none() || player.is_frozen || player.team_request.is_some() {
+                    data.afk_ticks = 0;
+                    continue;
+                }
+                match advance_afk_ticks(&mut data.afk_ticks, warn_ticks, spectate_ticks) {
+                    Some(AfkAction::Spectate) => {
+                        // The game mode moves the player when it next adds players,
+                        // like when a player chooses to spectate
+                        player.team_request = Some(TeamRequest {
+                            team: None,
+                            source: TeamRequestSource::Afk,
+                        });
+                    }
+                    Some(AfkAction::Warn) => warned.push(player_id),
+                    None => {}
+                }
+            }
+        }
+
+        for player_id in warned {
+            let msg = if spectate_ticks > warn_ticks {
+                format!(
+                    "You seem to be AFK, move or you will be moved to the spectators in {} seconds",
+                    (spectate_ticks - warn_ticks).div_ceil(100)
+                )
+            } else {
+                "You seem to be AFK".to_owned()
+            };
+            self.state
+                .players
+                .add_directed_server_chat_message(msg, player_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::afk::{advance_afk_ticks, AfkAction, AfkState};
+
+    #[test]
+    fn afk_timer() {
+        let mut state = AfkState::default();
+        let mut afk_ticks = 0;
+        let mut actions = vec![];
+        // The clock runs for 300 ticks, stops for 1000 ticks and then runs again
+        let times = (0..300u32)
+            .map(|i| 30000 - i)
+            .chain(std::iter::repeat_n(29701, 1000))
+            .chain((0..300u32).map(|i| 29700 - i));
+        for (tick, time) in times.enumerate() {
+            if state.clock_running(time) {
+                if let Some(action) = advance_afk_ticks(&mut afk_ticks, 200, 500) {
+                    actions.push((tick, action));
+                }
+            }
+        }
+        assert_eq!(
+            actions,
+            vec![(199, AfkAction::Warn), (1499, AfkAction::Spectate)]
+        );
+        assert_eq!(afk_ticks, 100);
+    }
+}
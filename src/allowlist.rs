@@ -0,0 +1,116 @@
+use ipnet::IpNet;
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Handle;
+use tracing::info;
+
+#[derive(Debug, Default)]
+struct AllowlistEntries {
+    /// Lowercase player names.
+    names: HashSet<String>,
+    ips: Vec<IpNet>,
+}
+
+impl AllowlistEntries {
+    fn parse(s: &str) -> Self {
+        let mut res = Self::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Ok(ip) = line.parse::<IpAddr>() {
+                res.ips.push(IpNet::from(ip));
+            } else if let Ok(range) = line.parse::<IpNet>() {
+                res.ips.push(range.trunc());
+            } else {
+                res.names.insert(line.to_lowercase());
+            }
+        }
+        res
+    }
+}
+
+/// List of player names, IP addresses and CIDR ranges that are allowed to join a private server.
+///
+/// The list is read from a text file with one entry per line, and reloaded when the file changes.
+pub struct Allowlist {
+    entries: Arc<Mutex<AllowlistEntries>>,
+    #[allow(dead_code)]
+    watcher: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+impl Allowlist {
+    pub async fn new(path: PathBuf) -> Result<Self, anyhow::Error> {
+        let entries = Arc::new(Mutex::new(read_allowlist_file(&path).await?));
+        let handle = Handle::current();
+
+        let mut watcher = new_debouncer(Duration::from_secs(1), None, {
+            let entries = entries.clone();
+            let path = path.clone();
+            move |event: DebounceEventResult| {
+                // Reading the file causes access events, which must not trigger another reload
+                let changed =
+                    event.is_ok_and(|events| events.iter().any(|event| !event.kind.is_access()));
+                if changed {
+                    let entries = entries.clone();
+                    let path = path.clone();
+                    handle.spawn(async move {
+                        if let Ok(res) = read_allowlist_file(&path).await {
+                            info!(
+                                "Reloaded allowlist with {} names and {} addresses",
+                                res.names.len(),
+                                res.ips.len()
+                            );
+                            *entries.lock() = res;
+                        }
+                    });
+                }
+            }
+        })?;
+        watcher.watch(&path, RecursiveMode::NonRecursive)?;
+        Ok(Self { entries, watcher })
+    }
+
+    /// Returns true if the player name or IP address is on the list.
+    pub fn is_allowed(&self, player_name: &str, ip: IpAddr) -> bool {
+        let entries = self.entries.lock();
+        entries.names.contains(&player_name.to_lowercase())
+            || entries.ips.iter().any(|range| range.contains(&ip))
+    }
+}
+
+async fn read_allowlist_file(path: &Path) -> Result<AllowlistEntries, tokio::io::Error> {
+    let mut f = tokio::fs::OpenOptions::new().read(true).open(path).await?;
+    let mut s = String::new();
+    f.read_to_string(&mut s).await?;
+    Ok(AllowlistEntries::parse(&s))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::allowlist::AllowlistEntries;
+    use std::net::IpAddr;
+
+    #[test]
+    fn parse_allowlist() {
+        let entries =
+            AllowlistEntries::parse("# League\nMigo\n10.1.2.3\n192.168.0.0/16\n2001:db8::/32\n");
+        assert!(entries.names.contains("migo"));
+        let contains = |ip: &str| {
+            let ip = ip.parse::<IpAddr>().unwrap();
+            entries.ips.iter().any(|range| range.contains(&ip))
+        };
+        assert!(contains("10.1.2.3"));
+        assert!(!contains("10.1.2.4"));
+        assert!(contains("192.168.44.5"));
+        assert!(contains("2001:db8::1"));
+        assert!(!contains("2001:db9::1"));
+    }
+}
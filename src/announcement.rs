@@ -0,0 +1,223 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::server::HQMServer;
+
+/// A time of day, optionally on a specific day of the week, like "20:00" or "Thu 20:00".
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduledTime {
+    pub weekday: Option<Weekday>,
+    pub time: NaiveTime,
+}
+
+impl ScheduledTime {
+    /// Returns true if this time occurs after `from` and no later than `to`.
+    fn is_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> bool {
+        let mut date = from.date();
+        while date <= to.date() {
+            let at = date.and_time(self.time);
+            if from < at && at <= to && self.weekday.is_none_or(|x| x == date.weekday()) {
+                return true;
+            }
+            date = date.succ_opt().unwrap();
+        }
+        false
+    }
+
+    /// Returns the next time this occurs after `now`.
+    fn next_after(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let mut date = now.date();
+        loop {
+            let at = date.and_time(self.time);
+            if at > now && self.weekday.is_none_or(|x| x == date.weekday()) {
+                return at;
+            }
+            date = date.succ_opt().unwrap();
+        }
+    }
+}
+
+impl FromStr for ScheduledTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (weekday, time) = match s.trim().split_once(' ') {
+            Some((weekday, time)) => (
+                Some(
+                    weekday
+                        .parse::<Weekday>()
+                        .map_err(|_| format!("invalid weekday {}", weekday))?,
+                ),
+                time,
+            ),
+            None => (None, s.trim()),
+        };
+        let time = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .map_err(|_| format!("invalid time {}, expected HH:MM", time))?;
+        Ok(Self { weekday, time })
+    }
+}
+
+/// A message that is repeated with a fixed interval.
+#[derive(Debug, Clone)]
+pub struct RepeatedAnnouncement {
+    pub interval: Duration,
+    pub message: String,
+}
+
+/// A message that is sent at a specific time of day.
+#[derive(Debug, Clone)]
+pub struct DailyAnnouncement {
+    pub at: ScheduledTime,
+    pub message: String,
+}
+
+/// Timed server announcements.
+///
+/// Messages can contain the template variables {red_score}, {blue_score}, {period}, {time},
+/// {players}, {player_max}, {server_name} and {next_match}.
+#[derive(Debug, Clone, Default)]
+pub struct AnnouncementConfiguration {
+    pub repeated: Vec<RepeatedAnnouncement>,
+    /// Messages that are sent one at a time, in order, every `rotation_interval`.
+    pub rotation: Vec<String>,
+    pub rotation_interval: Duration,
+    pub daily: Vec<DailyAnnouncement>,
+    /// Messages sent when a new period starts.
+    pub period_start: Vec<String>,
+    /// Scheduled match times, used for the {next_match} variable.
+    pub next_match: Vec<ScheduledTime>,
+}
+
+pub(crate) struct AnnouncementState {
+    last_repeated: Vec<Instant>,
+    last_rotation: Instant,
+    rotation_index: usize,
+    last_daily_check: DateTime<Local>,
+    last_period: u32,
+}
+
+impl AnnouncementState {
+    pub(crate) fn new(config: &AnnouncementConfiguration) -> Self {
+        let now = Instant::now();
+        Self {
+            last_repeated: vec![now; config.repeated.len()],
+            last_rotation: now,
+            rotation_index: 0,
+            last_daily_check: Local::now(),
+            last_period: 0,
+        }
+    }
+}
+
+impl HQMServer {
+    /// Sends the announcements that are due. Called every tick.
+    pub(crate) fn update_announcements(&mut self) {
+        let mut messages = vec![];
+        let config = &self.config.announcements;
+        let state = &mut self.announcements;
+
+        let now = Instant::now();
+        for (announcement, last) in config.repeated.iter().zip(state.last_repeated.iter_mut()) {
+            if now - *last >= announcement.interval {
+                *last = now;
+                messages.push(announcement.message.as_str());
+            }
+        }
+
+        if !config.rotation.is_empty() && now - state.last_rotation >= config.rotation_interval {
+            state.last_rotation = now;
+            let index = state.rotation_index % config.rotation.len();
+            state.rotation_index = index + 1;
+            messages.push(config.rotation[index].as_str());
+        }
+
+        let local_now = Local::now();
+        // Ticks only run while there are players, so skip messages that were due while the server was empty
+        if local_now - state.last_daily_check < chrono::Duration::minutes(1) {
+            for announcement in config.daily.iter() {
+                if announcement.at.is_between(
+                    state.last_daily_check.naive_local(),
+                    local_now.naive_local(),
+                ) {
+                    messages.push(announcement.message.as_str());
+                }
+            }
+        }
+        state.last_daily_check = local_now;
+
+        let period = self.state.scoreboard.period;
+        if period != state.last_period {
+            state.last_period = period;
+            if period > 0 && !self.state.scoreboard.game_over {
+                messages.extend(config.period_start.iter().map(String::as_str));
+            }
+        }
+
+        let messages: Vec<String> = messages
+            .into_iter()
+            .map(|message| self.format_announcement(message))
+            .collect();
+        for message in messages {
+            self.state.players.add_server_chat_message(message);
+        }
+    }
+
+    fn format_announcement(&self, template: &str) -> String {
+        let scoreboard = &self.state.scoreboard;
+        let time = scoreboard.time / 100;
+        let next_match = Local::now().naive_local();
+        let next_match = self
+            .config
+            .announcements
+            .next_match
+            .iter()
+            .map(|x| (x.next_after(next_match), x.weekday.is_some()))
+            .min_by_key(|(at, _)| *at)
+            .map_or(String::new(), |(at, has_weekday)| {
+                if has_weekday {
+                    at.format("%a %H:%M").to_string()
+                } else {
+                    at.format("%H:%M").to_string()
+                }
+            });
+        template
+            .replace("{red_score}", &scoreboard.red_score.to_string())
+            .replace("{blue_score}", &scoreboard.blue_score.to_string())
+            .replace("{period}", &scoreboard.period.to_string())
+            .replace("{time}", &format!("{}:{:02}", time / 60, time % 60))
+            .replace("{players}", &self.real_player_count().to_string())
+            .replace("{player_max}", &self.config.player_max.to_string())
+            .replace("{server_name}", &self.config.server_name)
+            .replace("{next_match}", &next_match)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::announcement::ScheduledTime;
+    use chrono::{NaiveDate, Weekday};
+
+    #[test]
+    fn scheduled_time() {
+        let t = "Thu 20:00".parse::<ScheduledTime>().unwrap();
+        assert_eq!(t.weekday, Some(Weekday::Thu));
+
+        // 2024-01-04 is a Thursday
+        let from = NaiveDate::from_ymd_opt(2024, 1, 4)
+            .unwrap()
+            .and_hms_opt(19, 59, 0)
+            .unwrap();
+        let to = from + chrono::Duration::minutes(2);
+        assert!(t.is_between(from, to));
+        assert!(!t.is_between(to, to + chrono::Duration::minutes(2)));
+        assert_eq!(
+            t.next_after(to),
+            from + chrono::Duration::days(7) + chrono::Duration::minutes(1)
+        );
+
+        assert!("25:00".parse::<ScheduledTime>().is_err());
+    }
+}
@@ -1,18 +1,20 @@
 use cached::{Cached, TimedCache};
-use itertools::Itertools;
+use chrono::{DateTime, SecondsFormat, Utc};
+use ipnet::IpNet;
 use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
 use notify_debouncer_full::{
     new_debouncer, DebounceEventHandler, DebounceEventResult, Debouncer, RecommendedCache,
 };
 use parking_lot::Mutex;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::future::Future;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::runtime::Handle;
+use tracing::warn;
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
 pub enum BanCheckResponse {
@@ -23,9 +25,25 @@ pub enum BanCheckResponse {
 
 pub trait BanCheck {
     fn check_ip_banned(&mut self, ip_addr: IpAddr) -> BanCheckResponse;
-    fn ban_ip(&mut self, ip_addr: IpAddr);
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo);
+    /// Bans all addresses in a CIDR range, like 1.2.3.0/24 or 2001:db8::/48.
+    fn ban_range(&mut self, range: IpNet, info: BanInfo);
 
     fn clear_all_bans(&mut self);
+
+    /// Removes the bans of an address, a CIDR range or a player name.
+    ///
+    /// Returns the removed bans, or [None] if the result is not known yet.
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>>;
+
+    /// Returns all active bans, newest first, or [None] if the list is still being loaded.
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>>;
+
+    /// Returns true if an address is covered by a ban that is already known,
+    /// without asking an external service.
+    fn is_known_banned(&mut self, ip_addr: IpAddr) -> bool {
+        self.check_ip_banned(ip_addr) == BanCheckResponse::Banned
+    }
 }
 
 impl<T> BanCheck for Box<T>
@@ -36,69 +54,395 @@ where
         self.as_mut().check_ip_banned(ip_addr)
     }
 
-    fn ban_ip(&mut self, ip_addr: IpAddr) {
-        self.as_mut().ban_ip(ip_addr)
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
+        self.as_mut().ban_ip(ip_addr, info)
+    }
+
+    fn ban_range(&mut self, range: IpNet, info: BanInfo) {
+        self.as_mut().ban_range(range, info)
     }
 
     fn clear_all_bans(&mut self) {
         self.as_mut().clear_all_bans();
     }
+
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>> {
+        self.as_mut().unban(target)
+    }
+
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>> {
+        self.as_mut().list_bans()
+    }
+
+    fn is_known_banned(&mut self, ip_addr: IpAddr) -> bool {
+        self.as_mut().is_known_banned(ip_addr)
+    }
+}
+
+/// Information about a ban.
+///
+/// All fields are optional, since older ban files only contain the banned addresses.
+#[derive(Debug, Clone, Default)]
+pub struct BanInfo {
+    /// Name of the banned player.
+    pub player_name: Option<String>,
+    /// Name of the administrator who issued the ban.
+    pub admin: Option<String>,
+    pub reason: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    /// Time when the ban expires, or [None] for permanent bans.
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl BanInfo {
+    /// Creates the information for a ban issued now.
+    pub fn new(
+        player_name: Option<&str>,
+        admin: &str,
+        reason: &str,
+        duration: Option<Duration>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            player_name: player_name.map(str::to_owned),
+            admin: Some(admin.to_owned()),
+            reason: Some(reason.to_owned()).filter(|x| !x.is_empty()),
+            created: Some(now),
+            expires: duration
+                .and_then(|x| chrono::Duration::from_std(x).ok())
+                .map(|x| now + x),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Parses the tab-separated key=value fields that follow the address in a ban file line.
+    pub(crate) fn parse(fields: &str) -> Self {
+        let mut res = Self::default();
+        for (key, value) in fields.split('\t').filter_map(|x| x.split_once('=')) {
+            let time = || {
+                DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|x| x.with_timezone(&Utc))
+            };
+            match key {
+                "name" => res.player_name = Some(value.to_owned()),
+                "admin" => res.admin = Some(value.to_owned()),
+                "reason" => res.reason = Some(value.to_owned()),
+                "created" => res.created = time(),
+                "expires" => res.expires = time(),
+                _ => {}
+            }
+        }
+        res
+    }
+
+    pub(crate) fn write_fields(&self, s: &mut String) {
+        let mut field = |key: &str, value: &str| {
+            let value = value.replace(['\t', '\n', '\r'], " ");
+            s.push_str(&format!("\t{}={}", key, value));
+        };
+        let time = |x: &DateTime<Utc>| x.to_rfc3339_opts(SecondsFormat::Secs, true);
+        if let Some(expires) = &self.expires {
+            field("expires", &time(expires));
+        }
+        if let Some(created) = &self.created {
+            field("created", &time(created));
+        }
+        if let Some(admin) = &self.admin {
+            field("admin", admin);
+        }
+        if let Some(player_name) = &self.player_name {
+            field("name", player_name);
+        }
+        if let Some(reason) = &self.reason {
+            field("reason", reason);
+        }
+    }
+}
+
+/// A banned address or CIDR range, with the information about the ban.
+#[derive(Debug, Clone)]
+pub struct BanEntry {
+    pub target: String,
+    pub info: BanInfo,
+}
+
+impl BanEntry {
+    /// Returns true if an unban target (address, CIDR range or player name) matches this ban.
+    fn matches(&self, target: &str) -> bool {
+        if let Ok(ip) = target.parse::<IpAddr>() {
+            self.target == ip.to_string()
+        } else if let Ok(range) = target.parse::<IpNet>() {
+            self.target == range.trunc().to_string()
+        } else {
+            self.info
+                .player_name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(target))
+        }
+    }
+}
+
+/// Banned IP addresses and address ranges.
+#[derive(Debug, Default, Clone)]
+pub struct BanList {
+    ips: HashMap<IpAddr, BanInfo>,
+    ranges: Vec<(IpNet, BanInfo)>,
+}
+
+impl BanList {
+    /// Returns the ban that applies to an address, ignoring expired bans.
+    pub fn get(&self, ip_addr: IpAddr) -> Option<&BanInfo> {
+        let now = Utc::now();
+        self.ips
+            .get(&ip_addr)
+            .into_iter()
+            .chain(
+                self.ranges
+                    .iter()
+                    .filter(|(range, _)| range.contains(&ip_addr))
+                    .map(|(_, info)| info),
+            )
+            .find(|info| !info.is_expired(now))
+    }
+
+    pub fn contains(&self, ip_addr: IpAddr) -> bool {
+        self.get(ip_addr).is_some()
+    }
+
+    pub fn insert_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
+        self.ips.insert(ip_addr, info);
+    }
+
+    pub fn insert_range(&mut self, range: IpNet, info: BanInfo) {
+        let range = range.trunc();
+        self.ranges.retain(|(x, _)| *x != range);
+        self.ranges.push((range, info));
+    }
+
+    /// Removes expired bans. Returns true if any were removed.
+    pub fn remove_expired(&mut self) -> bool {
+        let now = Utc::now();
+        let len = self.ips.len() + self.ranges.len();
+        self.ips.retain(|_, info| !info.is_expired(now));
+        self.ranges.retain(|(_, info)| !info.is_expired(now));
+        len != self.ips.len() + self.ranges.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.ips.clear();
+        self.ranges.clear();
+    }
+
+    /// Removes the bans that match an address, a CIDR range or a player name.
+    pub fn remove(&mut self, target: &str) -> Vec<BanEntry> {
+        let now = Utc::now();
+        let mut removed = vec![];
+        self.ips.retain(|ip, info| {
+            let entry = BanEntry {
+                target: ip.to_string(),
+                info: info.clone(),
+            };
+            let matches = entry.matches(target);
+            if matches && !info.is_expired(now) {
+                removed.push(entry);
+            }
+            !matches
+        });
+        self.ranges.retain(|(range, info)| {
+            let entry = BanEntry {
+                target: range.to_string(),
+                info: info.clone(),
+            };
+            let matches = entry.matches(target);
+            if matches && !info.is_expired(now) {
+                removed.push(entry);
+            }
+            !matches
+        });
+        removed
+    }
+
+    /// Returns all bans that haven't expired, newest first.
+    pub fn entries(&self) -> Vec<BanEntry> {
+        let now = Utc::now();
+        let ips = self.ips.iter().map(|(ip, info)| (ip.to_string(), info));
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(range, info)| (range.to_string(), info));
+        let mut res: Vec<_> = ips
+            .chain(ranges)
+            .filter(|(_, info)| !info.is_expired(now))
+            .map(|(target, info)| BanEntry {
+                target,
+                info: info.clone(),
+            })
+            .collect();
+        res.sort_by(|a, b| {
+            b.info
+                .created
+                .cmp(&a.info.created)
+                .then_with(|| a.target.cmp(&b.target))
+        });
+        res
+    }
+
+    /// Parses a ban file with one IP address or CIDR range per line.
+    ///
+    /// The address can be followed by tab-separated fields like expires=2024-01-01T12:00:00Z,
+    /// see [BanInfo]. Lines with only an address are permanent bans.
+    fn parse(s: &str) -> Self {
+        let mut res = Self::default();
+        for line in s.lines() {
+            let (target, fields) = line.split_once('\t').unwrap_or((line, ""));
+            let info = BanInfo::parse(fields);
+            if let Ok(ip) = target.trim().parse::<IpAddr>() {
+                res.insert_ip(ip, info);
+            } else if let Ok(range) = target.trim().parse::<IpNet>() {
+                res.insert_range(range, info);
+            }
+        }
+        res
+    }
+
+    fn to_file_string(&self) -> String {
+        let mut s = String::new();
+        let now = Utc::now();
+        let ips = self.ips.iter().map(|(ip, info)| (ip.to_string(), info));
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(range, info)| (range.to_string(), info));
+        for (target, info) in ips.chain(ranges) {
+            if !info.is_expired(now) {
+                s.push_str(&target);
+                info.write_fields(&mut s);
+                s.push('\n');
+            }
+        }
+        s
+    }
+}
+
+/// Parses a ban duration like "30m", "12h", "7d" or "2w". A number without unit is in minutes.
+pub fn parse_ban_duration(s: &str) -> Option<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number.parse::<u64>().ok().filter(|x| *x > 0)?;
+    let seconds = match unit {
+        "s" => 1,
+        "" | "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+/// Formats a ban duration for chat messages, like "2 hours".
+pub fn format_ban_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (number, unit) = [
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find(|(unit_seconds, _)| seconds >= *unit_seconds && seconds.is_multiple_of(*unit_seconds))
+    .map_or((seconds, "second"), |(unit_seconds, unit)| {
+        (seconds / unit_seconds, unit)
+    });
+    if number == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", number, unit)
+    }
 }
 
 pub struct InMemoryBanCheck {
-    bans: HashSet<IpAddr>,
+    bans: BanList,
 }
 
 impl InMemoryBanCheck {
     pub fn new() -> Self {
         Self {
-            bans: HashSet::new(),
+            bans: BanList::default(),
         }
     }
 }
 
 impl BanCheck for InMemoryBanCheck {
     fn check_ip_banned(&mut self, ip_addr: IpAddr) -> BanCheckResponse {
-        if self.bans.contains(&ip_addr) {
+        if self.bans.contains(ip_addr) {
             BanCheckResponse::Banned
         } else {
             BanCheckResponse::Allowed
         }
     }
 
-    fn ban_ip(&mut self, ip_addr: IpAddr) {
-        self.bans.insert(ip_addr);
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
+        self.bans.insert_ip(ip_addr, info);
+    }
+
+    fn ban_range(&mut self, range: IpNet, info: BanInfo) {
+        self.bans.insert_range(range, info);
     }
 
     fn clear_all_bans(&mut self) {
         self.bans.clear();
     }
+
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>> {
+        Some(self.bans.remove(target))
+    }
+
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>> {
+        Some(self.bans.entries())
+    }
 }
 
 pub struct FileBanCheck {
     file: PathBuf,
-    ban_list: Arc<Mutex<HashSet<IpAddr>>>,
+    ban_list: Arc<Mutex<BanList>>,
     watcher: Debouncer<RecommendedWatcher, RecommendedCache>,
+    /// If true, the file is only read, and bans can't be added or removed.
+    read_only: bool,
 }
 
 impl FileBanCheck {
     pub async fn new(path: PathBuf) -> Result<Self, anyhow::Error> {
-        let ban_list = Arc::new(Mutex::new(read_ban_file(&path).await?));
+        Self::open(path, false).await
+    }
+
+    /// Opens a ban file that is maintained elsewhere, like a shared community ban list.
+    pub async fn new_read_only(path: PathBuf) -> Result<Self, anyhow::Error> {
+        Self::open(path, true).await
+    }
+
+    async fn open(path: PathBuf, read_only: bool) -> Result<Self, anyhow::Error> {
+        let ban_list = Arc::new(Mutex::new(read_ban_file(&path, !read_only).await?));
         let handle = Handle::current();
 
         struct BanFileEventHandler {
             path: PathBuf,
-            ban_list: Arc<Mutex<HashSet<IpAddr>>>,
+            ban_list: Arc<Mutex<BanList>>,
             handle: Handle,
         }
 
         impl DebounceEventHandler for BanFileEventHandler {
             fn handle_event(&mut self, event: DebounceEventResult) {
-                if let Ok(_) = event {
+                // Reading the file causes access events, which must not trigger another reload
+                if event.is_ok_and(|events| events.iter().any(|event| !event.kind.is_access())) {
                     let ban_list = self.ban_list.clone();
                     let path = self.path.clone();
                     self.handle.spawn(async move {
-                        if let Ok(res) = read_ban_file(&path).await {
+                        if let Ok(res) = read_ban_file(&path, false).await {
                             {
                                 let mut ban_list = ban_list.lock();
                                 *ban_list = res;
@@ -122,6 +466,7 @@ impl FileBanCheck {
             ban_list,
             file: path,
             watcher,
+            read_only,
         })
     }
 }
@@ -129,18 +474,37 @@ impl FileBanCheck {
 impl BanCheck for FileBanCheck {
     fn check_ip_banned(&mut self, ip_addr: IpAddr) -> BanCheckResponse {
         let ban_list = self.ban_list.lock();
-        if ban_list.contains(&ip_addr) {
+        if ban_list.contains(ip_addr) {
             BanCheckResponse::Banned
         } else {
             BanCheckResponse::Allowed
         }
     }
 
-    fn ban_ip(&mut self, ip_addr: IpAddr) {
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
+        if self.read_only {
+            return;
+        }
+        let s = {
+            let mut ban_list = self.ban_list.lock();
+            ban_list.remove_expired();
+            ban_list.insert_ip(ip_addr, info);
+            ban_list.to_file_string()
+        };
+        let path = self.file.clone();
+
+        tokio::spawn(async move { write_ban_file(&path, &s).await });
+    }
+
+    fn ban_range(&mut self, range: IpNet, info: BanInfo) {
+        if self.read_only {
+            return;
+        }
         let s = {
             let mut ban_list = self.ban_list.lock();
-            ban_list.insert(ip_addr);
-            ban_list.iter().map(|x| format!("{}\n", x)).join("")
+            ban_list.remove_expired();
+            ban_list.insert_range(range, info);
+            ban_list.to_file_string()
         };
         let path = self.file.clone();
 
@@ -148,10 +512,13 @@ impl BanCheck for FileBanCheck {
     }
 
     fn clear_all_bans(&mut self) {
+        if self.read_only {
+            return;
+        }
         let s = {
             let mut ban_list = self.ban_list.lock();
             ban_list.clear();
-            ban_list.iter().map(|x| format!("{}\n", x)).join("")
+            ban_list.to_file_string()
         };
         let path = self.file.clone();
 
@@ -159,9 +526,30 @@ impl BanCheck for FileBanCheck {
             let _ = write_ban_file(&path, &s).await;
         });
     }
+
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>> {
+        if self.read_only {
+            return Some(vec![]);
+        }
+        let (removed, s) = {
+            let mut ban_list = self.ban_list.lock();
+            let removed = ban_list.remove(target);
+            (removed, ban_list.to_file_string())
+        };
+        if !removed.is_empty() {
+            let path = self.file.clone();
+
+            tokio::spawn(async move { write_ban_file(&path, &s).await });
+        }
+        Some(removed)
+    }
+
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>> {
+        Some(self.ban_list.lock().entries())
+    }
 }
 
-async fn write_ban_file(path: &Path, s: &str) -> Result<(), tokio::io::Error> {
+pub(crate) async fn write_ban_file(path: &Path, s: &str) -> Result<(), tokio::io::Error> {
     let mut f = tokio::fs::OpenOptions::new()
         .create(true)
         .write(true)
@@ -173,40 +561,178 @@ async fn write_ban_file(path: &Path, s: &str) -> Result<(), tokio::io::Error> {
     Ok(())
 }
 
-async fn read_ban_file(path: &Path) -> Result<HashSet<IpAddr>, tokio::io::Error> {
+async fn read_ban_file(path: &Path, create: bool) -> Result<BanList, tokio::io::Error> {
     let mut f = tokio::fs::OpenOptions::new()
-        .create(true)
+        .create(create)
         .read(true)
-        .write(true)
+        .write(create)
         .open(path)
         .await?;
     let mut s = String::new();
     f.read_to_string(&mut s).await?;
-    let mut res = HashSet::new();
-    for line in s.lines() {
-        if let Ok(ip) = line.parse::<IpAddr>() {
-            res.insert(ip);
+    Ok(BanList::parse(&s))
+}
+
+/// Combines several ban sources, like a local ban file, community ban lists and an external check.
+///
+/// A player is banned if any source bans them. New bans are written to the primary source and
+/// unbans apply to every source that can be changed. Clearing bans only applies to the primary
+/// source, so that a shared ban endpoint is not wiped from one server.
+pub struct LayeredBanCheck {
+    primary: Box<dyn BanCheck>,
+    others: Vec<Box<dyn BanCheck>>,
+}
+
+impl LayeredBanCheck {
+    pub fn new(primary: Box<dyn BanCheck>, others: Vec<Box<dyn BanCheck>>) -> Self {
+        Self { primary, others }
+    }
+}
+
+impl BanCheck for LayeredBanCheck {
+    fn check_ip_banned(&mut self, ip_addr: IpAddr) -> BanCheckResponse {
+        // Ask every source, so that external checks are started at the same time
+        let responses: Vec<_> = std::iter::once(&mut self.primary)
+            .chain(self.others.iter_mut())
+            .map(|source| source.check_ip_banned(ip_addr))
+            .collect();
+        if responses.contains(&BanCheckResponse::Banned) {
+            BanCheckResponse::Banned
+        } else if responses.contains(&BanCheckResponse::Pending) {
+            BanCheckResponse::Pending
+        } else {
+            BanCheckResponse::Allowed
+        }
+    }
+
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
+        self.primary.ban_ip(ip_addr, info)
+    }
+
+    fn ban_range(&mut self, range: IpNet, info: BanInfo) {
+        self.primary.ban_range(range, info)
+    }
+
+    fn clear_all_bans(&mut self) {
+        self.primary.clear_all_bans();
+    }
+
+    fn is_known_banned(&mut self, ip_addr: IpAddr) -> bool {
+        std::iter::once(&mut self.primary)
+            .chain(self.others.iter_mut())
+            .any(|source| source.is_known_banned(ip_addr))
+    }
+
+    /// Returns the removed bans of all sources, or [None] if no bans were removed yet
+    /// and some sources only removed them later.
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>> {
+        let mut removed = vec![];
+        let mut pending = false;
+        for source in std::iter::once(&mut self.primary).chain(self.others.iter_mut()) {
+            match source.unban(target) {
+                Some(entries) => removed.extend(entries),
+                None => pending = true,
+            }
+        }
+        if pending && removed.is_empty() {
+            None
+        } else {
+            Some(removed)
+        }
+    }
+
+    /// Returns the bans of all sources, newest first, or [None] if a source is still loading them.
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>> {
+        let mut entries = vec![];
+        let mut pending = false;
+        for source in std::iter::once(&mut self.primary).chain(self.others.iter_mut()) {
+            match source.list_bans() {
+                Some(list) => entries.extend(list),
+                None => pending = true,
+            }
+        }
+        if pending {
+            return None;
         }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.info.created));
+        Some(entries)
     }
-    Ok(res)
 }
 
 pub trait ExternalBanCheckRequests {
-    fn req_ip_banned(&mut self, ip_addr: IpAddr) -> impl Future<Output = bool> + Send + 'static;
-    fn req_ban_ip(&mut self, ip_addr: IpAddr) -> impl Future<Output = ()> + Send + 'static;
+    /// Checks if an address is banned. Returns [None] if the check failed.
+    fn req_ip_banned(
+        &mut self,
+        ip_addr: IpAddr,
+    ) -> impl Future<Output = Option<bool>> + Send + 'static;
+    fn req_ban_ip(
+        &mut self,
+        ip_addr: IpAddr,
+        info: BanInfo,
+    ) -> impl Future<Output = ()> + Send + 'static;
+    fn req_ban_range(
+        &mut self,
+        range: IpNet,
+        info: BanInfo,
+    ) -> impl Future<Output = ()> + Send + 'static;
 
     fn req_clear_all_bans(&mut self) -> impl Future<Output = ()> + Send + 'static;
+
+    fn req_unban(&mut self, target: String) -> impl Future<Output = ()> + Send + 'static;
+
+    /// Loads all active bans. Returns [None] if the request failed.
+    fn req_list_bans(&mut self) -> impl Future<Output = Option<Vec<BanEntry>>> + Send + 'static;
+}
+
+/// How [ExternalBanCheck] treats players while a check is pending or after it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BanCheckFallback {
+    /// Let players join.
+    Allow,
+    /// Ignore join requests until the check succeeds.
+    Deny,
 }
+
+/// Time a loaded ban list is shown by /bans before it is loaded again.
+const BAN_LIST_LIFESPAN: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct BanListCache {
+    entries: Option<Vec<BanEntry>>,
+    loaded: Option<Instant>,
+    loading: bool,
+}
+
 pub struct ExternalBanCheck<E> {
     cache: Arc<Mutex<TimedCache<IpAddr, BanCheckResponse>>>,
+    /// Ban list from the last /bans. It is only loaded again once it is older than
+    /// [BAN_LIST_LIFESPAN] or the bans have changed, and only one request runs at a time.
+    ban_list: Arc<Mutex<BanListCache>>,
     req: E,
+    fallback: BanCheckFallback,
 }
 
 impl<E: ExternalBanCheckRequests> ExternalBanCheck<E> {
     pub fn new(req: E) -> Self {
         Self {
             cache: Arc::new(Mutex::new(TimedCache::with_lifespan(10))),
+            ban_list: Arc::new(Mutex::new(BanListCache::default())),
             req,
+            fallback: BanCheckFallback::Allow,
+        }
+    }
+
+    /// Sets how players are treated while a check is pending or after it failed.
+    /// The default is [BanCheckFallback::Allow].
+    pub fn with_fallback(mut self, fallback: BanCheckFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    fn fallback_response(&self) -> BanCheckResponse {
+        match self.fallback {
+            BanCheckFallback::Allow => BanCheckResponse::Allowed,
+            BanCheckFallback::Deny => BanCheckResponse::Pending,
         }
     }
 }
@@ -215,44 +741,515 @@ impl<E: ExternalBanCheckRequests> BanCheck for ExternalBanCheck<E> {
     fn check_ip_banned(&mut self, ip_addr: IpAddr) -> BanCheckResponse {
         {
             let mut handle = self.cache.lock();
-            if let Some(res) = handle.cache_get(&ip_addr) {
-                return *res;
-            } else {
-                handle.cache_set(ip_addr, BanCheckResponse::Pending);
+            match handle.cache_get(&ip_addr) {
+                Some(BanCheckResponse::Pending) => return self.fallback_response(),
+                Some(res) => return *res,
+                None => {
+                    handle.cache_set(ip_addr, BanCheckResponse::Pending);
+                }
             }
         }
 
         let req = self.req.req_ip_banned(ip_addr);
         let cache = self.cache.clone();
+        let fallback = self.fallback_response();
         tokio::spawn(async move {
-            let res = req.await;
+            let res = match req.await {
+                Some(true) => BanCheckResponse::Banned,
+                Some(false) => BanCheckResponse::Allowed,
+                None => fallback,
+            };
             let mut handle = cache.lock();
-            handle.cache_set(
-                ip_addr,
-                if res {
-                    BanCheckResponse::Banned
-                } else {
-                    BanCheckResponse::Allowed
-                },
-            );
+            if res == BanCheckResponse::Pending {
+                // Failed check with the deny policy, try again on the next join request
+                handle.cache_remove(&ip_addr);
+            } else {
+                handle.cache_set(ip_addr, res);
+            }
         });
 
-        BanCheckResponse::Pending
+        self.fallback_response()
     }
 
-    fn ban_ip(&mut self, ip_addr: IpAddr) {
+    fn ban_ip(&mut self, ip_addr: IpAddr, info: BanInfo) {
         self.cache
             .lock()
             .cache_set(ip_addr, BanCheckResponse::Banned);
-        let req = self.req.req_ban_ip(ip_addr);
+        self.ban_list.lock().loaded = None;
+        let req = self.req.req_ban_ip(ip_addr, info);
+
+        tokio::spawn(req);
+    }
+
+    fn ban_range(&mut self, range: IpNet, info: BanInfo) {
+        // Cached responses for addresses in the range are no longer valid
+        self.cache.lock().cache_clear();
+        self.ban_list.lock().loaded = None;
+        let req = self.req.req_ban_range(range, info);
 
         tokio::spawn(req);
     }
 
     fn clear_all_bans(&mut self) {
         self.cache.lock().cache_clear();
+        self.ban_list.lock().loaded = None;
         let req = self.req.req_clear_all_bans();
 
         tokio::spawn(req);
     }
+
+    fn unban(&mut self, target: &str) -> Option<Vec<BanEntry>> {
+        self.cache.lock().cache_clear();
+        {
+            let mut ban_list = self.ban_list.lock();
+            if let Some(entries) = ban_list.entries.as_mut() {
+                entries.retain(|entry| !entry.matches(target));
+            }
+            ban_list.loaded = None;
+        }
+        let req = self.req.req_unban(target.to_owned());
+
+        tokio::spawn(req);
+        None
+    }
+
+    fn is_known_banned(&mut self, ip_addr: IpAddr) -> bool {
+        self.cache.lock().cache_get(&ip_addr) == Some(&BanCheckResponse::Banned)
+    }
+
+    fn list_bans(&mut self) -> Option<Vec<BanEntry>> {
+        let mut handle = self.ban_list.lock();
+        let fresh = handle
+            .loaded
+            .is_some_and(|loaded| loaded.elapsed() < BAN_LIST_LIFESPAN);
+        if !fresh && !handle.loading {
+            handle.loading = true;
+            let req = self.req.req_list_bans();
+            let ban_list = self.ban_list.clone();
+            tokio::spawn(async move {
+                let res = req.await;
+                let mut handle = ban_list.lock();
+                handle.loading = false;
+                if let Some(res) = res {
+                    handle.entries = Some(res);
+                    handle.loaded = Some(Instant::now());
+                }
+            });
+        }
+        handle.entries.clone()
+    }
+}
+
+/// Checks and stores bans with a REST endpoint, so several servers can share one ban list.
+///
+/// * `GET <url>?ip=<address>` answers 200 if the address is banned and 404 if it isn't.
+/// * `POST <url>` with a multipart form with the fields ip (address or CIDR range), name, admin,
+///   reason, created and expires bans an address or range.
+/// * `GET <url>` answers with all active bans, in the same format as the ban file.
+/// * `DELETE <url>?target=<target>` removes the bans of an address, CIDR range or player name.
+/// * `DELETE <url>` removes all bans.
+///
+/// If a token is configured, it is sent as a bearer token in the Authorization header.
+pub struct HttpBanCheckRequests {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpBanCheckRequests {
+    pub fn new(
+        url: String,
+        token: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { url, token, client })
+    }
+
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, &self.url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn ban_request(&self, target: String, info: BanInfo) -> impl Future<Output = ()> + Send {
+        let time = |x: DateTime<Utc>| x.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut form = reqwest::multipart::Form::new().text("ip", target.clone());
+        for (key, value) in [
+            ("name", info.player_name),
+            ("admin", info.admin),
+            ("reason", info.reason),
+            ("created", info.created.map(time)),
+            ("expires", info.expires.map(time)),
+        ] {
+            if let Some(value) = value {
+                form = form.text(key, value);
+            }
+        }
+        let request = self.request(reqwest::Method::POST).multipart(form);
+        async move {
+            match request.send().await.and_then(|x| x.error_for_status()) {
+                Ok(_) => {}
+                Err(e) => warn!("Could not send ban of {}: {}", target, e),
+            }
+        }
+    }
+}
+
+impl ExternalBanCheckRequests for HttpBanCheckRequests {
+    fn req_ip_banned(
+        &mut self,
+        ip_addr: IpAddr,
+    ) -> impl Future<Output = Option<bool>> + Send + 'static {
+        let request = self
+            .request(reqwest::Method::GET)
+            .query(&[("ip", ip_addr.to_string())]);
+        async move {
+            match request.send().await {
+                Ok(res) if res.status() == reqwest::StatusCode::NOT_FOUND => Some(false),
+                Ok(res) if res.status().is_success() => Some(true),
+                Ok(res) => {
+                    warn!("Ban check for {} failed: {}", ip_addr, res.status());
+                    None
+                }
+                Err(e) => {
+                    warn!("Ban check for {} failed: {}", ip_addr, e);
+                    None
+                }
+            }
+        }
+    }
+
+    fn req_ban_ip(
+        &mut self,
+        ip_addr: IpAddr,
+        info: BanInfo,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        self.ban_request(ip_addr.to_string(), info)
+    }
+
+    fn req_ban_range(
+        &mut self,
+        range: IpNet,
+        info: BanInfo,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        self.ban_request(range.to_string(), info)
+    }
+
+    fn req_clear_all_bans(&mut self) -> impl Future<Output = ()> + Send + 'static {
+        let request = self.request(reqwest::Method::DELETE);
+        async move {
+            if let Err(e) = request.send().await.and_then(|x| x.error_for_status()) {
+                warn!("Could not clear bans: {}", e);
+            }
+        }
+    }
+
+    fn req_unban(&mut self, target: String) -> impl Future<Output = ()> + Send + 'static {
+        let request = self
+            .request(reqwest::Method::DELETE)
+            .query(&[("target", &target)]);
+        async move {
+            if let Err(e) = request.send().await.and_then(|x| x.error_for_status()) {
+                warn!("Could not remove ban of {}: {}", target, e);
+            }
+        }
+    }
+
+    fn req_list_bans(&mut self) -> impl Future<Output = Option<Vec<BanEntry>>> + Send + 'static {
+        let request = self.request(reqwest::Method::GET);
+        async move {
+            let res = match request.send().await.and_then(|x| x.error_for_status()) {
+                Ok(res) => res.text().await,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(s) => Some(BanList::parse(&s).entries()),
+                Err(e) => {
+                    warn!("Could not load bans: {}", e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ban::{
+        format_ban_duration, parse_ban_duration, BanCheck, BanCheckFallback, BanCheckResponse,
+        BanInfo, BanList, ExternalBanCheck, ExternalBanCheckRequests, HttpBanCheckRequests,
+        InMemoryBanCheck, LayeredBanCheck,
+    };
+    use ipnet::IpNet;
+    use parking_lot::Mutex;
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal stand-in for a ban endpoint. 10.0.0.1 is banned, 10.0.0.3 makes the check fail.
+    /// Returns the address and the received requests as (request line, headers and body).
+    async fn ban_endpoint() -> (SocketAddr, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut data = vec![];
+                let mut buf = [0u8; 4096];
+                // Read the headers, then the body with the given length
+                let (head, body_len) = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                    let s = String::from_utf8_lossy(&data).into_owned();
+                    if let Some(end) = s.find("\r\n\r\n") {
+                        let len = s[..end]
+                            .lines()
+                            .find_map(|x| {
+                                x.to_lowercase()
+                                    .strip_prefix("content-length: ")?
+                                    .parse()
+                                    .ok()
+                            })
+                            .unwrap_or(0);
+                        break (end + 4, len);
+                    }
+                };
+                while data.len() < head + body_len {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                }
+                let s = String::from_utf8_lossy(&data).into_owned();
+                let (request_line, rest) = s.split_once("\r\n").unwrap();
+                let status = if request_line.contains("ip=10.0.0.1 ") {
+                    "200 OK"
+                } else if request_line.contains("ip=10.0.0.3 ") {
+                    "500 Internal Server Error"
+                } else if request_line.starts_with("GET /?ip=") {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                received
+                    .lock()
+                    .push((request_line.to_owned(), rest.to_owned()));
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (addr, requests)
+    }
+
+    /// Waits until a cached check result is no longer the given response.
+    async fn wait_for_check(
+        check: &mut impl BanCheck,
+        ip: IpAddr,
+        pending: BanCheckResponse,
+    ) -> BanCheckResponse {
+        for _ in 0..100 {
+            let res = check.check_ip_banned(ip);
+            if res != pending {
+                return res;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        pending
+    }
+
+    #[tokio::test]
+    async fn http_ban_endpoint() {
+        let (addr, requests) = ban_endpoint().await;
+        let url = format!("http://{}/", addr);
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        let mut req =
+            HttpBanCheckRequests::new(url, Some("secret".to_owned()), Duration::from_secs(5))
+                .unwrap();
+        assert_eq!(req.req_ip_banned(ip("10.0.0.1")).await, Some(true));
+        assert_eq!(req.req_ip_banned(ip("10.0.0.2")).await, Some(false));
+        assert_eq!(req.req_ip_banned(ip("10.0.0.3")).await, None);
+
+        let info = BanInfo::new(Some("Troll"), "Migo", "Spam", None);
+        req.req_ban_ip(ip("10.0.0.2"), info).await;
+        req.req_clear_all_bans().await;
+
+        let requests = requests.lock().clone();
+        assert_eq!(requests.len(), 5);
+        assert!(requests
+            .iter()
+            .all(|(_, rest)| rest.contains("authorization: Bearer secret")));
+        let (request_line, rest) = &requests[3];
+        assert!(request_line.starts_with("POST / "));
+        assert!(rest.contains("name=\"ip\"\r\n\r\n10.0.0.2"));
+        assert!(rest.contains("name=\"reason\"\r\n\r\nSpam"));
+        assert!(requests[4].0.starts_with("DELETE / "));
+    }
+
+    #[tokio::test]
+    async fn http_ban_list_requests() {
+        let (addr, requests) = ban_endpoint().await;
+        let url = format!("http://{}/", addr);
+        let req = HttpBanCheckRequests::new(url, None, Duration::from_secs(5)).unwrap();
+        let mut check = ExternalBanCheck::new(req);
+        let mut ban_list = None;
+        for _ in 0..100 {
+            ban_list = check.list_bans();
+            if ban_list.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(ban_list.map(|x| x.len()), Some(0));
+        // The loaded list is reused instead of being requested again
+        assert!(check.list_bans().is_some());
+        let list_requests = requests
+            .lock()
+            .iter()
+            .filter(|(request_line, _)| request_line.starts_with("GET / "))
+            .count();
+        assert_eq!(list_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn http_ban_check_fallback() {
+        let (addr, _) = ban_endpoint().await;
+        let url = format!("http://{}/", addr);
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        let req = HttpBanCheckRequests::new(url, None, Duration::from_secs(5)).unwrap();
+        let mut check = ExternalBanCheck::new(req);
+        assert_eq!(
+            wait_for_check(&mut check, ip("10.0.0.1"), BanCheckResponse::Allowed).await,
+            BanCheckResponse::Banned
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            check.check_ip_banned(ip("10.0.0.3")),
+            BanCheckResponse::Allowed
+        );
+
+        let url = format!("http://{}/", addr);
+        let req = HttpBanCheckRequests::new(url, None, Duration::from_secs(5)).unwrap();
+        let mut check = ExternalBanCheck::new(req).with_fallback(BanCheckFallback::Deny);
+        assert_eq!(
+            wait_for_check(&mut check, ip("10.0.0.2"), BanCheckResponse::Pending).await,
+            BanCheckResponse::Allowed
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            check.check_ip_banned(ip("10.0.0.3")),
+            BanCheckResponse::Pending
+        );
+    }
+
+    #[test]
+    fn ban_file() {
+        let s = "10.0.0.1\n\
+            10.0.1.0/24\texpires=2000-01-01T00:00:00Z\n\
+            10.0.2.0/24\texpires=2999-01-01T00:00:00Z\tadmin=Migo\tname=Troll\treason=Spam\n";
+        let bans = BanList::parse(s);
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        assert!(bans.contains(ip("10.0.0.1")));
+        assert!(!bans.contains(ip("10.0.1.5")));
+        let ban = bans.get(ip("10.0.2.5")).unwrap();
+        assert_eq!(ban.reason.as_deref(), Some("Spam"));
+        assert_eq!(ban.player_name.as_deref(), Some("Troll"));
+
+        // Expired bans are not written back
+        let mut bans = BanList::parse(&bans.to_file_string());
+        assert!(!bans.contains(ip("10.0.1.5")));
+        assert!(bans.contains(ip("10.0.2.5")));
+        assert_eq!(bans.entries().len(), 2);
+
+        let removed = bans.remove("troll");
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].target, "10.0.2.0/24");
+        assert!(!bans.contains(ip("10.0.2.5")));
+        assert_eq!(bans.remove("10.0.0.1").len(), 1);
+        assert!(bans.entries().is_empty());
+    }
+
+    #[test]
+    fn layered_ban_check() {
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        let mut primary = InMemoryBanCheck::new();
+        let mut other = InMemoryBanCheck::new();
+        primary.ban_ip(ip("10.0.0.1"), BanInfo::new(None, "Migo", "", None));
+        other.ban_ip(ip("10.0.0.2"), BanInfo::new(None, "Migo", "", None));
+        let mut bans = LayeredBanCheck::new(Box::new(primary), vec![Box::new(other)]);
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.2")),
+            BanCheckResponse::Banned
+        );
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.3")),
+            BanCheckResponse::Allowed
+        );
+
+        // New bans go to the primary source, the list contains the bans of all sources
+        bans.ban_ip(ip("10.0.0.3"), BanInfo::new(None, "Migo", "", None));
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.3")),
+            BanCheckResponse::Banned
+        );
+        assert_eq!(bans.list_bans().unwrap().len(), 3);
+
+        let removed = bans.unban("10.0.0.2").unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.2")),
+            BanCheckResponse::Allowed
+        );
+        assert_eq!(bans.unban("10.0.0.2").unwrap().len(), 0);
+
+        // An address can still be covered by a range after its own ban is removed
+        let range = "10.0.2.0/24".parse::<IpNet>().unwrap();
+        bans.ban_range(range, BanInfo::new(None, "Migo", "", None));
+        bans.ban_ip(ip("10.0.2.5"), BanInfo::new(None, "Migo", "", None));
+        assert_eq!(bans.unban("10.0.2.5").unwrap().len(), 1);
+        assert!(bans.is_known_banned(ip("10.0.2.5")));
+        bans.unban("10.0.2.0/24");
+        assert!(!bans.is_known_banned(ip("10.0.2.5")));
+
+        // Only the primary source is cleared
+        bans.ban_ip(ip("10.0.0.4"), BanInfo::new(None, "Migo", "", None));
+        let mut other = InMemoryBanCheck::new();
+        other.ban_ip(ip("10.0.0.5"), BanInfo::new(None, "Migo", "", None));
+        bans.others.push(Box::new(other));
+        bans.clear_all_bans();
+        assert_eq!(bans.list_bans().unwrap().len(), 1);
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.1")),
+            BanCheckResponse::Allowed
+        );
+        assert_eq!(
+            bans.check_ip_banned(ip("10.0.0.5")),
+            BanCheckResponse::Banned
+        );
+    }
+
+    #[test]
+    fn ban_duration() {
+        assert_eq!(parse_ban_duration("90"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(
+            parse_ban_duration("2h"),
+            Some(Duration::from_secs(2 * 60 * 60))
+        );
+        assert_eq!(parse_ban_duration("spam"), None);
+        assert_eq!(parse_ban_duration("0d"), None);
+        assert_eq!(
+            format_ban_duration(Duration::from_secs(2 * 60 * 60)),
+            "2 hours"
+        );
+        assert_eq!(
+            format_ban_duration(Duration::from_secs(7 * 24 * 60 * 60)),
+            "1 week"
+        );
+    }
 }
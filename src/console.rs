@@ -0,0 +1,409 @@
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Creates a new console channel.
+///
+/// The [ConsoleHandle] can be cloned and used by any number of consoles to send commands and read output,
+/// while the [ConsoleReceiver] is set as the console of the [ServerConfiguration](crate::ServerConfiguration).
+pub fn console_channel() -> (ConsoleHandle, ConsoleReceiver) {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (messages, _) = broadcast::channel(1024);
+    let output = ConsoleOutput {
+        messages,
+        player_names: Default::default(),
+    };
+    (
+        ConsoleHandle {
+            commands: command_sender,
+            output: output.clone(),
+        },
+        ConsoleReceiver {
+            commands: Arc::new(Mutex::new(Some(command_receiver))),
+            output,
+        },
+    )
+}
+
+/// Handle used to send commands to a running server and to receive its output.
+///
+/// Lines starting with "/" are handled like chat commands sent by an administrator,
+/// other lines are broadcast as server chat messages.
+#[derive(Clone)]
+pub struct ConsoleHandle {
+    commands: mpsc::UnboundedSender<ConsoleCommand>,
+    output: ConsoleOutput,
+}
+
+impl ConsoleHandle {
+    /// Sends a line to the server. Returns false if the server is no longer running.
+    pub fn send_line(&self, line: impl Into<String>) -> bool {
+        self.commands
+            .send(ConsoleCommand::Line(line.into()))
+            .is_ok()
+    }
+
+    /// Asks the server to save its recordings and stop. Returns false if the server is no longer running.
+    pub fn shutdown(&self) -> bool {
+        self.commands.send(ConsoleCommand::Shutdown).is_ok()
+    }
+
+    /// Subscribes to the console output, which contains both the server log and command responses.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<str>> {
+        self.output.messages.subscribe()
+    }
+
+    /// Gets the names of the players currently in the server.
+    pub fn player_names(&self) -> Vec<String> {
+        self.output.player_names.lock().clone()
+    }
+}
+
+pub(crate) enum ConsoleCommand {
+    Line(String),
+    Shutdown,
+}
+
+/// The server side of a console channel.
+///
+/// It is cloned together with the configuration, but only the first server started with it
+/// receives the commands.
+#[derive(Clone)]
+pub struct ConsoleReceiver {
+    commands: Arc<Mutex<Option<mpsc::UnboundedReceiver<ConsoleCommand>>>>,
+    output: ConsoleOutput,
+}
+
+impl ConsoleReceiver {
+    pub(crate) fn take(&self) -> Option<(mpsc::UnboundedReceiver<ConsoleCommand>, ConsoleOutput)> {
+        let commands = self.commands.lock().take()?;
+        Some((commands, self.output.clone()))
+    }
+}
+
+impl std::fmt::Debug for ConsoleReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ConsoleReceiver")
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ConsoleOutput {
+    messages: broadcast::Sender<Arc<str>>,
+    player_names: Arc<Mutex<Vec<String>>>,
+}
+
+impl ConsoleOutput {
+    pub(crate) fn send(&self, message: &str) {
+        let _ = self.messages.send(message.into());
+    }
+
+    pub(crate) fn set_player_names(&self, player_names: Vec<String>) {
+        *self.player_names.lock() = player_names;
+    }
+}
+
+/// Log writer that forwards each log line to the console output.
+pub struct ConsoleLogWriter {
+    output: broadcast::Sender<Arc<str>>,
+}
+
+impl Write for ConsoleLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let s = String::from_utf8_lossy(buf);
+        for line in s.lines() {
+            let _ = self.output.send(line.into());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for ConsoleHandle {
+    type Writer = ConsoleLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ConsoleLogWriter {
+            output: self.output.messages.clone(),
+        }
+    }
+}
+
+/// Time a remote console connection has to send the password.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Listens for remote console connections on a TCP port.
+///
+/// Clients must send the password as the first line before any commands are accepted.
+/// The connection is not encrypted and the password is sent in plain text, so the port
+/// must only be bound to localhost, or be reached through an SSH tunnel or VPN.
+pub async fn run_tcp_console(
+    addr: SocketAddr,
+    password: String,
+    console: ConsoleHandle,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Remote console listening at address {:?}", addr);
+    if !addr.ip().is_loopback() {
+        warn!("Remote console is not bound to localhost, the password is sent in plain text");
+    }
+    accept_tcp_console(listener, password.into(), console, LOGIN_TIMEOUT).await
+}
+
+async fn accept_tcp_console(
+    listener: TcpListener,
+    password: Arc<str>,
+    console: ConsoleHandle,
+    login_timeout: Duration,
+) -> std::io::Result<()> {
+    loop {
+        let (stream, remote_addr) = listener.accept().await?;
+        let console = console.clone();
+        let password = password.clone();
+        tokio::spawn(async move {
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            match tokio::time::timeout(login_timeout, lines.next_line()).await {
+                Ok(Ok(Some(line))) if line.trim_end() == password.as_ref() => {
+                    info!("Remote console connection from {:?}", remote_addr);
+                    if write.write_all(b"Logged in\n").await.is_ok() {
+                        handle_console_connection(lines, write, console).await;
+                    }
+                    info!("Remote console connection from {:?} closed", remote_addr);
+                }
+                Err(_) => {
+                    warn!(
+                        "Remote console connection from {:?} did not log in in time",
+                        remote_addr
+                    );
+                }
+                _ => {
+                    warn!(
+                        "Remote console connection from {:?} used wrong password",
+                        remote_addr
+                    );
+                    let _ = write.write_all(b"Wrong password\n").await;
+                }
+            }
+        });
+    }
+}
+
+/// Listens for remote console connections on a Unix socket.
+///
+/// Access is controlled by the file permissions of the socket, so no password is required.
+#[cfg(unix)]
+pub async fn run_unix_console(
+    path: std::path::PathBuf,
+    console: ConsoleHandle,
+) -> std::io::Result<()> {
+    // Remove the socket left behind by a previous run
+    if tokio::fs::metadata(&path).await.is_ok() {
+        tokio::fs::remove_file(&path).await?;
+    }
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    info!("Remote console listening at {:?}", path);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let console = console.clone();
+        tokio::spawn(async move {
+            info!("Remote console connection on Unix socket");
+            let (read, write) = stream.into_split();
+            let lines = BufReader::new(read).lines();
+            handle_console_connection(lines, write, console).await;
+            info!("Remote console connection on Unix socket closed");
+        });
+    }
+}
+
+async fn handle_console_connection<R, W>(
+    mut lines: tokio::io::Lines<BufReader<R>>,
+    mut write: W,
+    console: ConsoleHandle,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut output = console.subscribe();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                match line {
+                    Ok(Some(line)) => {
+                        let line = line.trim();
+                        if !line.is_empty() && !console.send_line(line) {
+                            break;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            msg = output.recv() => {
+                match msg {
+                    Ok(msg) => {
+                        let res = async {
+                            write.write_all(msg.as_bytes()).await?;
+                            write.write_all(b"\n").await
+                        };
+                        if res.await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+struct TerminalConsoleHelper {
+    console: ConsoleHandle,
+}
+
+impl Completer for TerminalConsoleHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+        let prefix = line[start..pos].to_lowercase();
+        let candidates = self
+            .console
+            .player_names()
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&prefix))
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for TerminalConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for TerminalConsoleHelper {}
+
+impl Validator for TerminalConsoleHelper {}
+
+impl Helper for TerminalConsoleHelper {}
+
+/// Reads console lines from the terminal, with tab completion of player names.
+///
+/// This method blocks until the terminal is closed, so it should be run in its own thread.
+pub fn run_terminal_console(console: ConsoleHandle) -> rustyline::Result<()> {
+    let mut editor = Editor::<TerminalConsoleHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(TerminalConsoleHelper {
+        console: console.clone(),
+    }));
+
+    let mut printer = editor.create_external_printer()?;
+    let mut output = console.subscribe();
+    std::thread::spawn(move || loop {
+        match output.blocking_recv() {
+            Ok(msg) => {
+                if printer.print(format!("{}\n", msg)).is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    });
+
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                let line = line.trim();
+                if !line.is_empty() {
+                    let _ = editor.add_history_entry(line);
+                    if !console.send_line(line) {
+                        break;
+                    }
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                // Ctrl+C in the console stops the server, like it would without a console
+                console.shutdown();
+                break;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::console::{accept_tcp_console, console_channel, ConsoleCommand};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[tokio::test]
+    async fn tcp_console_login() {
+        let (console, receiver) = console_channel();
+        let (mut commands, _) = receiver.take().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(accept_tcp_console(
+            listener,
+            "secret".into(),
+            console,
+            Duration::from_millis(200),
+        ));
+
+        // Wrong password
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"wrong\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "Wrong password\n");
+
+        // No password before the timeout, the connection is closed without a response
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut response = String::new();
+        let read =
+            tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+                .await;
+        assert!(matches!(read, Ok(Ok(0))));
+
+        // Correct password, commands are forwarded to the server
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"secret\n/kick 3\n").await.unwrap();
+        assert_eq!(
+            lines.next_line().await.unwrap().as_deref(),
+            Some("Logged in")
+        );
+        let command = tokio::time::timeout(Duration::from_secs(5), commands.recv()).await;
+        assert!(matches!(
+            command,
+            Ok(Some(ConsoleCommand::Line(line))) if line == "/kick 3"
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::server::{HQMServer, PlayerListExt};
+
+/// Limits for players and join requests per IP address. A value of 0 disables that limit.
+///
+/// Addresses in reserved_ips are not affected by the player and join limits.
+#[derive(Debug, Clone)]
+pub struct FloodConfiguration {
+    /// Maximum number of players connected from one IP address.
+    pub max_players_per_ip: usize,
+    /// Maximum number of joins from one IP address within join_interval.
+    pub max_joins: usize,
+    pub join_interval: Duration,
+    /// Maximum number of Join and ServerInfo packets per second from one IP address.
+    /// Addresses that send more are ignored for block_time.
+    pub max_packets_per_second: u32,
+    pub block_time: Duration,
+}
+
+impl Default for FloodConfiguration {
+    fn default() -> Self {
+        Self {
+            max_players_per_ip: 0,
+            max_joins: 0,
+            join_interval: Duration::from_secs(60),
+            max_packets_per_second: 0,
+            block_time: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FloodState {
+    joins: HashMap<IpAddr, Vec<Instant>>,
+    /// Start of the current one second window and the number of packets in it.
+    packets: HashMap<IpAddr, (Instant, u32)>,
+    blocked: HashMap<IpAddr, Instant>,
+    last_cleanup: Option<Instant>,
+}
+
+impl FloodState {
+    fn cleanup(&mut self, now: Instant, join_interval: Duration) {
+        if self
+            .last_cleanup
+            .is_some_and(|x| now - x < Duration::from_secs(10))
+        {
+            return;
+        }
+        self.last_cleanup = Some(now);
+        self.joins.retain(|_, joins| {
+            joins.retain(|x| now - *x < join_interval);
+            !joins.is_empty()
+        });
+        self.packets
+            .retain(|_, (start, _)| now - *start < Duration::from_secs(1));
+        self.blocked.retain(|_, until| *until > now);
+    }
+
+    /// Counts a packet from an address. Returns false if the address is blocked.
+    fn check_packet(&mut self, ip: IpAddr, now: Instant, config: &FloodConfiguration) -> bool {
+        self.cleanup(now, config.join_interval);
+        if config.max_packets_per_second == 0 {
+            return true;
+        }
+        if self.blocked.get(&ip).is_some_and(|until| *until > now) {
+            return false;
+        }
+        let (start, count) = self.packets.entry(ip).or_insert((now, 0));
+        if now - *start >= Duration::from_secs(1) {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count > config.max_packets_per_second {
+            warn!(
+                "Blocked {} for {} seconds for sending too many join or server info requests",
+                ip,
+                config.block_time.as_secs()
+            );
+            self.packets.remove(&ip);
+            self.blocked.insert(ip, now + config.block_time);
+            return false;
+        }
+        true
+    }
+
+    /// Checks the limits for a player joining from an address that already has `players` players.
+    fn can_join(
+        &self,
+        ip: IpAddr,
+        players: usize,
+        now: Instant,
+        config: &FloodConfiguration,
+    ) -> bool {
+        if config.max_players_per_ip > 0 && players >= config.max_players_per_ip {
+            return false;
+        }
+        if config.max_joins > 0 {
+            let joins = self.joins.get(&ip).map_or(0, |joins| {
+                joins
+                    .iter()
+                    .filter(|x| now - **x < config.join_interval)
+                    .count()
+            });
+            if joins >= config.max_joins {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn record_join(&mut self, ip: IpAddr, now: Instant, config: &FloodConfiguration) {
+        if config.max_joins == 0 {
+            return;
+        }
+        let joins = self.joins.entry(ip).or_default();
+        joins.retain(|x| now - *x < config.join_interval);
+        joins.push(now);
+    }
+}
+
+impl HQMServer {
+    /// Counts a Join or ServerInfo packet. Returns false if the packet should be ignored,
+    /// because the address is blocked for flooding.
+    pub(crate) fn check_packet_flood(&mut self, ip: IpAddr) -> bool {
+        self.flood
+            .check_packet(ip, Instant::now(), &self.config.flood)
+    }
+
+    /// Checks the player and join limits for the address of a joining player.
+    ///
+    /// Only addresses in reserved_ips are exempt, because player names are not verified when joining.
+    pub(crate) fn check_join_limits(&self, ip: IpAddr) -> bool {
+        if self.config.slots.reserved_ips.contains(&ip) {
+            return true;
+        }
+        let players = self
+            .state
+            .players
+            .players
+            .iter_players()
+            .filter(|(_, player)| player.ip() == Some(ip))
+            .count();
+        self.flood
+            .can_join(ip, players, Instant::now(), &self.config.flood)
+    }
+
+    /// Records a join for the join rate limit.
+    pub(crate) fn record_join(&mut self, ip: IpAddr) {
+        self.flood
+            .record_join(ip, Instant::now(), &self.config.flood);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flood::{FloodConfiguration, FloodState};
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn join_limits() {
+        let config = FloodConfiguration {
+            max_players_per_ip: 2,
+            max_joins: 3,
+            join_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other_ip: IpAddr = "10.0.0.2".parse().unwrap();
+        let mut state = FloodState::default();
+        let now = Instant::now();
+        assert!(state.can_join(ip, 1, now, &config));
+        assert!(!state.can_join(ip, 2, now, &config));
+
+        for _ in 0..3 {
+            assert!(state.can_join(ip, 0, now, &config));
+            state.record_join(ip, now, &config);
+        }
+        assert!(!state.can_join(ip, 0, now, &config));
+        assert!(state.can_join(other_ip, 0, now, &config));
+        assert!(state.can_join(ip, 0, now + Duration::from_secs(60), &config));
+    }
+
+    #[test]
+    fn packet_flood() {
+        let config = FloodConfiguration {
+            max_packets_per_second: 5,
+            block_time: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut state = FloodState::default();
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert!(state.check_packet(ip, now, &config));
+        }
+        // A new one second window starts
+        let later = now + Duration::from_secs(1);
+        for _ in 0..5 {
+            assert!(state.check_packet(ip, later, &config));
+        }
+        assert!(!state.check_packet(ip, later, &config));
+        assert!(!state.check_packet(ip, later + Duration::from_secs(29), &config));
+        assert!(state.check_packet(ip, later + Duration::from_secs(31), &config));
+
+        let mut state = FloodState::default();
+        let disabled = FloodConfiguration::default();
+        assert!((0..1000).all(|_| state.check_packet(ip, now, &disabled)));
+    }
+}
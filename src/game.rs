@@ -73,6 +73,81 @@ impl Default for PhysicsConfiguration {
     }
 }
 
+impl PhysicsConfiguration {
+    /// Properties that can be changed, and the factor between the value used in
+    /// configuration files and the value used by the physics engine.
+    const PROPERTIES: [(&'static str, f32); 9] = [
+        ("gravity", 10000.0),
+        ("player_acceleration", 10000.0),
+        ("player_deceleration", 10000.0),
+        ("max_player_speed", 100.0),
+        ("max_player_shift_speed", 100.0),
+        ("puck_rink_friction", 1.0),
+        ("player_turning", 10000.0),
+        ("player_shift_turning", 10000.0),
+        ("player_shift_acceleration", 10000.0),
+    ];
+
+    /// Names of the properties that can be changed with [set](Self::set).
+    pub fn property_names() -> impl Iterator<Item = &'static str> {
+        Self::PROPERTIES.iter().map(|(name, _)| *name)
+    }
+
+    fn property_mut(&mut self, property: &str) -> Option<&mut f32> {
+        match property {
+            "gravity" => Some(&mut self.gravity),
+            "player_acceleration" => Some(&mut self.player_acceleration),
+            "player_deceleration" => Some(&mut self.player_deceleration),
+            "max_player_speed" => Some(&mut self.max_player_speed),
+            "max_player_shift_speed" => Some(&mut self.max_player_shift_speed),
+            "puck_rink_friction" => Some(&mut self.puck_rink_friction),
+            "player_turning" => Some(&mut self.player_turning),
+            "player_shift_turning" => Some(&mut self.player_shift_turning),
+            "player_shift_acceleration" => Some(&mut self.player_shift_acceleration),
+            _ => None,
+        }
+    }
+
+    /// Sets a property using the same units as the [Physics] section of the configuration file.
+    pub fn set(&mut self, property: &str, value: &str) -> Result<(), String> {
+        if property == "limit_jump_speed" {
+            self.limit_jump_speed = match value {
+                "true" | "on" => true,
+                "false" | "off" => false,
+                _ => return Err(format!("Invalid value {} for {}", value, property)),
+            };
+            return Ok(());
+        }
+        let Some((_, factor)) = Self::PROPERTIES.iter().find(|(name, _)| *name == property) else {
+            return Err(format!("Unknown physics property {}", property));
+        };
+        let value = value
+            .parse::<f32>()
+            .ok()
+            .filter(|x| x.is_finite())
+            .ok_or_else(|| format!("Invalid value {} for {}", value, property))?;
+        *self.property_mut(property).unwrap() = value / factor;
+        Ok(())
+    }
+
+    /// Returns all properties using the same units as the configuration file.
+    pub fn values(&self) -> Vec<(&'static str, String)> {
+        let mut config = self.clone();
+        let mut res: Vec<_> = Self::PROPERTIES
+            .iter()
+            .map(|(name, factor)| {
+                let value = *config.property_mut(name).unwrap() * factor;
+                // Round off the noise from converting between units
+                let value = format!("{:.5}", value);
+                let value = value.trim_end_matches('0').trim_end_matches('.');
+                (*name, value.to_owned())
+            })
+            .collect();
+        res.push(("limit_jump_speed", self.limit_jump_speed.to_string()));
+        res
+    }
+}
+
 /// Represents a line in the HQM rink.
 #[derive(Debug, Clone)]
 pub struct RinkLine {
@@ -483,7 +558,7 @@ impl SkaterCollisionBall {
 
 /// Key and mouse inputs sent from the client to the server.
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlayerInput {
     /// Stick angle. Normal range is -1 to 1.
     pub stick_angle: f32,
@@ -715,3 +790,43 @@ pub enum PhysicsEvent {
     PuckPassedGoalLine { team: Team, puck: usize },
     PuckTouchedNet { team: Team, puck: usize },
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::PhysicsConfiguration;
+
+    #[test]
+    fn physics_properties() {
+        let mut config = PhysicsConfiguration::default();
+        let value = |config: &PhysicsConfiguration, name: &str| {
+            config
+                .values()
+                .into_iter()
+                .find(|(x, _)| *x == name)
+                .map(|(_, value)| value)
+                .unwrap()
+        };
+        // Values are shown in the units of the configuration file
+        assert_eq!(value(&config, "gravity"), "6.80555");
+        assert_eq!(value(&config, "max_player_speed"), "5");
+
+        config.set("gravity", "10").unwrap();
+        assert!((config.gravity - 0.001).abs() < 1e-9);
+        assert_eq!(value(&config, "gravity"), "10");
+        config.set("puck_rink_friction", "0.1").unwrap();
+        assert_eq!(config.puck_rink_friction, 0.1);
+        config.set("limit_jump_speed", "on").unwrap();
+        assert!(config.limit_jump_speed);
+        assert_eq!(value(&config, "limit_jump_speed"), "true");
+
+        assert!(config.set("gravity", "x").is_err());
+        assert!(config.set("gravity", "inf").is_err());
+        assert!(config.set("limit_jump_speed", "1").is_err());
+        assert!(config.set("unknown", "1").is_err());
+        assert!((config.gravity - 0.001).abs() < 1e-9);
+        assert_eq!(
+            PhysicsConfiguration::property_names().count() + 1,
+            config.values().len()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::admin::AdminPermission;
 use crate::game::PlayerId;
 use crate::game::Team;
 use crate::gamemode::ServerMut;
@@ -6,38 +7,64 @@ use crate::gamemode::match_util::{
     IcingConfiguration, Match, OffsideConfiguration, OffsideLineConfiguration,
     TwoLinePassConfiguration, ALLOWED_POSITIONS,
 };
+use reborrow::ReborrowMut;
 use tracing::info;
 
 impl Match {
     pub fn reset_game(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             info!("{} ({}) reset game", name, player_id);
-            let msg = format!("Game reset by {}", name);
+            self.reset_game_by(server, &name);
+        }
+    }
 
-            server.new_game(self.get_initial_game_values());
+    /// Resets the game.
+    pub fn reset_game_by(&mut self, mut server: ServerMut, reset_by: &str) {
+        let msg = format!("Game reset by {}", reset_by);
 
-            server.players_mut().add_server_chat_message(msg);
-        }
+        server.new_game(self.get_initial_game_values());
+
+        server.players_mut().add_server_chat_message(msg);
     }
 
     pub fn start_game(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
-            let values = server.scoreboard_mut();
-            if values.period == 0 && values.time > 1 {
+            if self.start_game_by(server.rb_mut(), &name) {
                 info!("{} ({}) started game", name, player_id);
-                let msg = format!("Game started by {}", name);
-                self.paused = false;
-                values.time = 1;
-
-                server.players_mut().add_server_chat_message(msg);
             }
         }
     }
 
+    /// Starts the game if it is still in warmup.
+    ///
+    /// Returns false if the game has already started.
+    pub fn start_game_by(&mut self, mut server: ServerMut, started_by: &str) -> bool {
+        let values = server.scoreboard_mut();
+        if values.period == 0 && values.time > 1 {
+            let msg = format!("Game started by {}", started_by);
+            self.paused = false;
+            values.time = 1;
+
+            server.players_mut().add_server_chat_message(msg);
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn pause(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             self.paused = true;
             if self.pause_timer > 0 && self.pause_timer < self.config.time_break {
                 // If we're currently in a break, with very little time left,
@@ -52,7 +79,10 @@ impl Match {
     }
 
     pub fn unpause(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             self.paused = false;
             let name = player.name();
             info!("{} ({}) resumed game", name, player_id);
@@ -63,7 +93,10 @@ impl Match {
     }
 
     pub fn set_clock(&mut self, mut server: ServerMut, input_time: u32, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             server.scoreboard_mut().time = input_time;
 
@@ -89,7 +122,10 @@ impl Match {
         input_score: u32,
         player_id: PlayerId,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             match input_team {
                 Team::Red => {
@@ -118,7 +154,10 @@ impl Match {
     }
 
     pub fn set_period(&mut self, mut server: ServerMut, input_period: u32, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             server.scoreboard_mut().period = input_period;
 
@@ -135,7 +174,10 @@ impl Match {
         input_period: u32,
         player_id: PlayerId,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             self.config.periods = input_period;
             let name = player.name();
 
@@ -150,7 +192,10 @@ impl Match {
     }
 
     pub fn set_icing_rule(&mut self, mut server: ServerMut, player_id: PlayerId, rule: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             let name = player.name();
 
             match rule {
@@ -181,7 +226,10 @@ impl Match {
     }
 
     pub fn set_offside_line(&mut self, mut server: ServerMut, player_id: PlayerId, rule: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             let name = player.name();
 
             match rule {
@@ -205,7 +253,10 @@ impl Match {
     }
 
     pub fn set_twoline_pass(&mut self, mut server: ServerMut, player_id: PlayerId, rule: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             match rule {
                 "off" => {
                     self.config.twoline_pass = TwoLinePassConfiguration::Off;
@@ -266,7 +317,10 @@ impl Match {
     }
 
     pub fn set_offside_rule(&mut self, mut server: ServerMut, player_id: PlayerId, rule: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             match rule {
                 "on" | "delayed" => {
                     self.config.offside = OffsideConfiguration::Delayed;
@@ -300,7 +354,10 @@ impl Match {
     }
 
     pub fn set_goal_replay(&mut self, mut server: ServerMut, player_id: PlayerId, setting: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             match setting {
                 "on" => {
                     self.config.goal_replay = true;
@@ -322,7 +379,10 @@ impl Match {
     }
 
     pub fn set_first_to_rule(&mut self, mut server: ServerMut, player_id: PlayerId, num: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             let num = if num == "off" {
                 Some(0)
             } else {
@@ -349,7 +409,10 @@ impl Match {
     }
 
     pub fn set_mercy_rule(&mut self, mut server: ServerMut, player_id: PlayerId, num: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             let num = if num == "off" {
                 Some(0)
             } else {
@@ -377,7 +440,10 @@ impl Match {
 
     pub fn faceoff(&mut self, mut server: ServerMut, player_id: PlayerId) {
         if !server.scoreboard().game_over {
-            if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+            if let Some(player) = server
+                .players_mut()
+                .check_permission_or_deny(player_id, AdminPermission::Game)
+            {
                 self.pause_timer = 5 * 100;
                 self.paused = false; // Unpause if it's paused as well
 
@@ -463,7 +529,10 @@ impl Match {
     }
 
     pub fn set_spawn_offset(&mut self, mut server: ServerMut, player_id: PlayerId, rule: f32) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             self.config.spawn_point_offset = rule;
 
             let name = player.name();
@@ -482,7 +551,10 @@ impl Match {
         player_id: PlayerId,
         rule: f32,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             self.config.spawn_player_altitude = rule;
             let name = player.name();
 
@@ -501,7 +573,10 @@ impl Match {
         player_id: PlayerId,
         rule: f32,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             self.config.spawn_puck_altitude = rule;
             let name = player.name();
 
@@ -520,7 +595,10 @@ impl Match {
         player_id: PlayerId,
         setting: &str,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             let name = player.name();
             let v = match setting {
                 "on" | "true" => Some(true),
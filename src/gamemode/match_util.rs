@@ -11,6 +11,7 @@ use reborrow::{Reborrow, ReborrowMut};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::str::FromStr;
 
 pub const ALLOWED_POSITIONS: [&str; 18] = [
     "C", "LW", "RW", "LD", "RD", "G", "LM", "RM", "LLM", "RRM", "LLD", "RRD", "CM", "CD", "LW2",
@@ -37,6 +38,38 @@ pub enum RinkFaceoffSpot {
     Offside(Team, RinkSide),
 }
 
+impl FromStr for RinkFaceoffSpot {
+    type Err = ();
+
+    /// Parses faceoff spot names used in commands, like "center", "red_zone_left" or "blue_neutral_right".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "center" {
+            return Ok(RinkFaceoffSpot::Center);
+        }
+        let mut parts = s.split('_');
+        let team = match parts.next() {
+            Some("red") => Team::Red,
+            Some("blue") => Team::Blue,
+            _ => return Err(()),
+        };
+        let zone = parts.next();
+        let side = match parts.next() {
+            Some("left") => RinkSide::LowerHalfZ,
+            Some("right") => RinkSide::HigherHalfZ,
+            _ => return Err(()),
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+        match zone {
+            Some("zone") => Ok(RinkFaceoffSpot::DefensiveZone(team, side)),
+            Some("neutral") => Ok(RinkFaceoffSpot::Offside(team, side)),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct MatchConfiguration {
     pub time_period: u32,
     pub time_warmup: u32,
@@ -1175,7 +1208,7 @@ fn setup_position(
     }
 }
 
-fn get_faceoff_spot(
+pub(crate) fn get_faceoff_spot(
     rink: &Rink,
     spot: RinkFaceoffSpot,
     spawn_point_offset: f32,
@@ -1,10 +1,11 @@
+use crate::admin::AdminPermission;
 use crate::game::{
     PhysicsEvent, PlayerId, PlayerIndex, PlayerInput, Puck, Rink, ScoreboardValues, SkaterObject,
     Team,
 };
 use crate::server::{
     HQMServer, HQMServerPlayer, HQMServerPlayersAndMessages, HQMTickHistory, PlayerListExt,
-    ServerPlayerData,
+    ServerPlayerData, TeamRequest,
 };
 use crate::ServerConfiguration;
 use nalgebra::{Point3, Rotation3};
@@ -19,8 +20,9 @@ pub mod util;
 pub mod warmup;
 
 mod match_commands;
-mod match_util;
+pub(crate) mod match_util;
 pub mod standard_match;
+pub mod switch;
 
 /// Specifies the server game behaviour.
 ///
@@ -72,6 +74,14 @@ pub trait GameMode {
     fn include_tick_in_recording(&self, _server: Server) -> bool {
         false
     }
+
+    /// Returns true if players are allowed to vote for this game action.
+    fn supports_vote(&self, _vote: GameVote) -> bool {
+        false
+    }
+
+    /// Called when a player vote for a game action has passed.
+    fn vote_passed(&mut self, _server: ServerMut, _vote: GameVote) {}
 }
 
 /// A struct containing the individual parts of a [ServerMut].
@@ -156,6 +166,32 @@ impl<'a> ServerMut<'a> {
         self.server.new_game(v)
     }
 
+    /// Increases the number of puck slots without starting a new game, moving skaters out of the
+    /// new slots. Returns false if there are not enough free object slots.
+    pub fn add_puck_slots(&mut self, puck_slots: usize) -> bool {
+        self.server.state.add_puck_slots(puck_slots)
+    }
+
+    /// Records a privileged action by an administrator in the audit log.
+    ///
+    /// Commands handled by a game mode are audited with their arguments once they pass a
+    /// permission check. Game modes only need to call this to record a target player or a failure,
+    /// which replaces that record.
+    pub fn audit(
+        &mut self,
+        admin_player_id: PlayerId,
+        command: &str,
+        args: &str,
+        target_player_id: Option<PlayerId>,
+        result: Result<(), &str>,
+    ) {
+        if let Some(command) = &mut self.server.state.players.game_mode_command {
+            command.audited = true;
+        }
+        self.server
+            .audit(admin_player_id, command, args, target_player_id, result)
+    }
+
     pub fn rink(&self) -> &Rink {
         &self.server.rink
     }
@@ -399,15 +435,25 @@ impl<'a> ServerPlayersMut<'a> {
             .map(|player| ServerPlayerMut { id, player })
     }
 
-    /// Returns a player object if the player is admin, otherwise sends a message telling the user to log in first.
-    pub fn check_admin_or_deny(&mut self, player_id: PlayerId) -> Option<ServerPlayer> {
-        self.state
-            .players
-            .check_admin_or_deny(player_id)
-            .map(|player| ServerPlayer {
-                id: player_id,
-                player,
-            })
+    /// Returns a player object if the player is logged in with the given permission, otherwise sends a message telling the user why the command was denied.
+    pub fn check_permission_or_deny(
+        &mut self,
+        player_id: PlayerId,
+        permission: AdminPermission,
+    ) -> Option<ServerPlayer<'_>> {
+        let command = self.state.game_mode_command.as_mut();
+        let player = self.state.players.check_permission_or_deny(
+            player_id,
+            permission,
+            command.as_ref().map_or("", |x| x.command.as_str()),
+        )?;
+        if let Some(command) = command {
+            command.permitted = true;
+        }
+        Some(ServerPlayer {
+            id: player_id,
+            player,
+        })
     }
 
     /// Convenience method to count the number of players currently in the red or blue team.
@@ -415,6 +461,11 @@ impl<'a> ServerPlayersMut<'a> {
         let a = self.rb();
         a.count_team_members()
     }
+
+    /// Returns true if teams have been locked by an administrator with /lockteams.
+    pub fn teams_locked(&self) -> bool {
+        self.state.teams_locked
+    }
 }
 
 /// Immutable handle to player state.
@@ -463,6 +514,11 @@ impl<'a> ServerPlayers<'a> {
         }
         (red_player_count, blue_player_count)
     }
+
+    /// Returns true if teams have been locked by an administrator with /lockteams.
+    pub fn teams_locked(&self) -> bool {
+        self.state.teams_locked
+    }
 }
 
 /// Mutable handle to player who is connected to the server.
@@ -492,7 +548,11 @@ impl<'a> ServerPlayerMut<'a> {
     }
 
     pub fn is_admin(&self) -> bool {
-        self.player.is_admin
+        self.player.is_admin()
+    }
+
+    pub fn has_permission(&self, permission: AdminPermission) -> bool {
+        self.player.has_permission(permission)
     }
 
     pub fn name(&self) -> Rc<str> {
@@ -517,10 +577,15 @@ impl<'a> ServerPlayerMut<'a> {
         self.player.add_directed_server_chat_message(message);
     }
 
+    pub(crate) fn take_team_request(&mut self) -> Option<TeamRequest> {
+        self.player.team_request.take()
+    }
+
     pub fn player_type(&self) -> ServerPlayerType {
         match self.player.data {
             ServerPlayerData::NetworkPlayer { .. } => ServerPlayerType::Player,
             ServerPlayerData::Bot { .. } => ServerPlayerType::Bot,
+            ServerPlayerData::Console { .. } => ServerPlayerType::Console,
         }
     }
 }
@@ -529,6 +594,7 @@ impl<'a> ServerPlayerMut<'a> {
 pub enum ServerPlayerType {
     Player,
     Bot,
+    Console,
 }
 
 /// Immutable handle to player who is connected to the server.
@@ -552,7 +618,11 @@ impl<'a> ServerPlayer<'a> {
     }
 
     pub fn is_admin(&self) -> bool {
-        self.player.is_admin
+        self.player.is_admin()
+    }
+
+    pub fn has_permission(&self, permission: AdminPermission) -> bool {
+        self.player.has_permission(permission)
     }
 
     pub fn name(&self) -> Rc<str> {
@@ -570,6 +640,7 @@ impl<'a> ServerPlayer<'a> {
         match self.player.data {
             ServerPlayerData::NetworkPlayer { .. } => ServerPlayerType::Player,
             ServerPlayerData::Bot { .. } => ServerPlayerType::Bot,
+            ServerPlayerData::Console { .. } => ServerPlayerType::Console,
         }
     }
 }
@@ -585,6 +656,16 @@ pub enum ExitReason {
     Disconnected,
     Timeout,
     AdminKicked,
+    VoteKicked,
+    /// Removed as an idle spectator to make room for another player.
+    Evicted,
+}
+
+/// Game actions that players can vote for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameVote {
+    Reset,
+    Start,
 }
 
 pub trait PuckExt {
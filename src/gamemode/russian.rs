@@ -1,3 +1,4 @@
+use crate::admin::AdminPermission;
 use nalgebra::{Point3, Rotation3, Vector3};
 use std::collections::HashMap;
 use tracing::info;
@@ -6,7 +7,7 @@ use crate::game::{PhysicsEvent, PlayerId};
 use crate::game::{PlayerIndex, Puck, ScoreboardValues, Team};
 use crate::gamemode::util::add_players;
 use crate::gamemode::{
-    ExitReason, GameMode, InitialGameValues, PuckExt, Server, ServerMut, ServerMutParts,
+    ExitReason, GameMode, GameVote, InitialGameValues, PuckExt, Server, ServerMut, ServerMutParts,
 };
 use crate::physics;
 use reborrow::ReborrowMut;
@@ -188,15 +189,22 @@ impl RussianGameMode {
     }
 
     fn reset_game(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             info!("{} ({}) reset game", name, player_id);
-            let msg = format!("Game reset by {}", name);
+            self.reset_game_by(server, &name);
+        }
+    }
 
-            server.new_game(self.get_initial_game_values());
+    fn reset_game_by(&mut self, mut server: ServerMut, reset_by: &str) {
+        let msg = format!("Game reset by {}", reset_by);
 
-            server.players_mut().add_server_chat_message(msg);
-        }
+        server.new_game(self.get_initial_game_values());
+
+        server.players_mut().add_server_chat_message(msg);
     }
 
     fn force_player_off_ice(
@@ -205,7 +213,10 @@ impl RussianGameMode {
         admin_player_id: PlayerId,
         force_player_index: PlayerIndex,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(admin_player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+        {
             let admin_player_name = player.name();
 
             if let Some(force_player) = server.players().get_by_index(force_player_index) {
@@ -220,6 +231,13 @@ impl RussianGameMode {
                         "{} ({}) forced {} ({}) off ice",
                         admin_player_name, admin_player_id, force_player_name, force_player_index
                     );
+                    server.audit(
+                        admin_player_id,
+                        "fs",
+                        &force_player_index.to_string(),
+                        Some(force_player_id),
+                        Ok(()),
+                    );
                     server.players_mut().add_server_chat_message(msg);
                     self.team_switch_timer.insert(force_player_id, 500);
                 }
@@ -411,4 +429,14 @@ impl GameMode for RussianGameMode {
     fn include_tick_in_recording(&self, _server: Server) -> bool {
         !matches!(self.status, RussianStatus::WaitingForGame)
     }
+
+    fn supports_vote(&self, vote: GameVote) -> bool {
+        vote == GameVote::Reset
+    }
+
+    fn vote_passed(&mut self, server: ServerMut, vote: GameVote) {
+        if vote == GameVote::Reset {
+            self.reset_game_by(server, "vote");
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::admin::AdminPermission;
 use nalgebra::{Point3, Rotation3, Vector3};
 use reborrow::ReborrowMut;
 use std::collections::HashMap;
@@ -9,7 +10,7 @@ use crate::game::{PhysicsEvent, PlayerId};
 use crate::game::{PlayerIndex, Puck, ScoreboardValues, Team};
 use crate::gamemode::util::{add_players, get_spawnpoint, SpawnPoint};
 use crate::gamemode::{
-    ExitReason, GameMode, InitialGameValues, PuckExt, Server, ServerMut, ServerMutParts,
+    ExitReason, GameMode, GameVote, InitialGameValues, PuckExt, Server, ServerMut, ServerMutParts,
 };
 
 #[derive(Debug, Clone)]
@@ -242,15 +243,22 @@ impl ShootoutGameMode {
     }
 
     fn reset_game(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             let name = player.name();
             info!("{} ({}) reset game", name, player_id);
-            let msg = format!("Game reset by {}", name);
+            self.reset_game_by(server, &name);
+        }
+    }
 
-            server.new_game(self.get_initial_game_values());
+    fn reset_game_by(&mut self, mut server: ServerMut, reset_by: &str) {
+        let msg = format!("Game reset by {}", reset_by);
 
-            server.players_mut().add_server_chat_message(msg);
-        }
+        server.new_game(self.get_initial_game_values());
+
+        server.players_mut().add_server_chat_message(msg);
     }
 
     fn force_player_off_ice(
@@ -259,7 +267,10 @@ impl ShootoutGameMode {
         admin_player_id: PlayerId,
         force_player_index: PlayerIndex,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(admin_player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+        {
             let admin_player_name = player.name();
 
             if let Some(force_player) = server.players().get_by_index(force_player_index) {
@@ -274,6 +285,13 @@ impl ShootoutGameMode {
                         "{} ({}) forced {} ({}) off ice",
                         admin_player_name, admin_player_id, force_player_name, force_player_index
                     );
+                    server.audit(
+                        admin_player_id,
+                        "fs",
+                        &force_player_index.to_string(),
+                        Some(force_player_id),
+                        Ok(()),
+                    );
                     server.players_mut().add_server_chat_message(msg);
                     self.team_switch_timer.insert(force_player_id, 500);
                 }
@@ -288,7 +306,10 @@ impl ShootoutGameMode {
         input_score: u32,
         player_id: PlayerId,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             match input_team {
                 Team::Red => {
                     let name = player.name();
@@ -325,7 +346,10 @@ impl ShootoutGameMode {
         if input_round == 0 {
             return;
         }
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             if let ShootoutStatus::Game {
                 state: _,
                 round,
@@ -360,7 +384,10 @@ impl ShootoutGameMode {
         if input_round == 0 {
             return;
         }
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             if let ShootoutStatus::Game {
                 state: _,
                 round,
@@ -389,7 +416,10 @@ impl ShootoutGameMode {
     }
 
     fn pause(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             self.paused = true;
             let name = player.name();
 
@@ -400,7 +430,10 @@ impl ShootoutGameMode {
     }
 
     fn unpause(&mut self, mut server: ServerMut, player_id: PlayerId) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Game)
+        {
             self.paused = false;
             if let ShootoutStatus::Game {
                 state: ShootoutAttemptState::Over { timer, .. },
@@ -662,4 +695,14 @@ impl GameMode for ShootoutGameMode {
     fn include_tick_in_recording(&self, _server: Server) -> bool {
         !matches!(self.status, ShootoutStatus::WaitingForGame)
     }
+
+    fn supports_vote(&self, vote: GameVote) -> bool {
+        vote == GameVote::Reset
+    }
+
+    fn vote_passed(&mut self, server: ServerMut, vote: GameVote) {
+        if vote == GameVote::Reset {
+            self.reset_game_by(server, "vote");
+        }
+    }
 }
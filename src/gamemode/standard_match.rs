@@ -1,3 +1,4 @@
+use crate::admin::AdminPermission;
 use tracing::info;
 
 use std::collections::{HashMap, HashSet};
@@ -9,7 +10,12 @@ pub use crate::gamemode::match_util::{
     TwoLinePassConfiguration, ALLOWED_POSITIONS,
 };
 use crate::gamemode::util::{add_players, get_spawnpoint, SpawnPoint};
-use crate::gamemode::{ExitReason, GameMode, InitialGameValues, Server, ServerMut, ServerMutParts};
+use crate::gamemode::{
+    ExitReason, GameMode, GameVote, InitialGameValues, Server, ServerMut, ServerMutParts,
+};
+
+/// Largest team size that can be set with /set teamsize or /mode match.
+pub const MAX_TEAM_SIZE: usize = 15;
 
 pub struct StandardMatchGameMode {
     pub m: Match,
@@ -61,7 +67,10 @@ impl StandardMatchGameMode {
         admin_player_id: PlayerId,
         force_player_index: PlayerIndex,
     ) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(admin_player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+        {
             let admin_player_name = player.name();
 
             if let Some(force_player) = server.players().get_by_index(force_player_index) {
@@ -76,6 +85,13 @@ impl StandardMatchGameMode {
                         "{} ({}) forced {} ({}) off ice",
                         admin_player_name, admin_player_id, force_player_name, force_player_id
                     );
+                    server.audit(
+                        admin_player_id,
+                        "fs",
+                        &force_player_index.to_string(),
+                        Some(force_player_id),
+                        Ok(()),
+                    );
                     server.players_mut().add_server_chat_message(msg);
                     self.team_switch_timer.insert(force_player_id, 500);
                 }
@@ -84,9 +100,12 @@ impl StandardMatchGameMode {
     }
 
     pub(crate) fn set_team_size(&mut self, mut server: ServerMut, player_id: PlayerId, size: &str) {
-        if let Some(player) = server.players_mut().check_admin_or_deny(player_id) {
+        if let Some(player) = server
+            .players_mut()
+            .check_permission_or_deny(player_id, AdminPermission::Rules)
+        {
             if let Ok(new_num) = size.parse::<usize>() {
-                if new_num > 0 && new_num <= 15 {
+                if (1..=MAX_TEAM_SIZE).contains(&new_num) {
                     self.team_max = new_num;
                     let name = player.name();
 
@@ -326,4 +345,17 @@ impl GameMode for StandardMatchGameMode {
     fn include_tick_in_recording(&self, server: Server) -> bool {
         server.scoreboard().period > 0
     }
+
+    fn supports_vote(&self, _vote: GameVote) -> bool {
+        true
+    }
+
+    fn vote_passed(&mut self, server: ServerMut, vote: GameVote) {
+        match vote {
+            GameVote::Reset => self.m.reset_game_by(server, "vote"),
+            GameVote::Start => {
+                self.m.start_game_by(server, "vote");
+            }
+        }
+    }
 }
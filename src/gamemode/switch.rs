@@ -0,0 +1,138 @@
+use reborrow::ReborrowMut;
+use tracing::info;
+
+use crate::admin::AdminPermission;
+use crate::game::{PhysicsEvent, PlayerId};
+use crate::gamemode::{ExitReason, GameMode, GameVote, InitialGameValues, Server, ServerMut};
+
+/// Creates a game mode from the argument given to /mode, like the number of attempts in "/mode shootout 5".
+///
+/// The argument is empty if none was given. Returns None if the argument is invalid.
+pub type GameModeFactory = Box<dyn Fn(&str) -> Option<Box<dyn GameMode>>>;
+
+/// Game mode that lets administrators switch between other game modes at runtime with /mode.
+pub struct SwitchableGameMode {
+    modes: Vec<(String, GameModeFactory)>,
+    current_name: String,
+    current: Box<dyn GameMode>,
+}
+
+impl SwitchableGameMode {
+    /// Creates a new switchable game mode that starts with `initial`, which must be one of the modes.
+    pub fn new(modes: Vec<(String, GameModeFactory)>, initial: &str) -> Option<Self> {
+        let current = modes
+            .iter()
+            .find(|(name, _)| name == initial)
+            .and_then(|(_, factory)| factory(""))?;
+        Some(Self {
+            modes,
+            current_name: initial.to_owned(),
+            current,
+        })
+    }
+
+    fn change_mode(&mut self, mut server: ServerMut, arg: &str, admin_player_id: PlayerId) {
+        let Some(admin_player_name) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Rules)
+            .map(|player| player.name())
+        else {
+            return;
+        };
+
+        let (mode_name, mode_arg) = arg.split_once(' ').unwrap_or((arg, ""));
+        if mode_name.is_empty() {
+            let names: Vec<&str> = self.modes.iter().map(|(name, _)| name.as_str()).collect();
+            let msg = format!(
+                "Current mode is {}, available modes: {}",
+                self.current_name,
+                names.join(", ")
+            );
+            server
+                .players_mut()
+                .add_directed_server_chat_message(msg, admin_player_id);
+            return;
+        }
+        let Some((name, factory)) = self.modes.iter().find(|(name, _)| name == mode_name) else {
+            server
+                .players_mut()
+                .add_directed_server_chat_message("Unknown game mode", admin_player_id);
+            server.audit(admin_player_id, "mode", arg, None, Err("unknown mode"));
+            return;
+        };
+        let Some(mode) = factory(mode_arg.trim()) else {
+            let msg = format!("Invalid setting for game mode {}", name);
+            server
+                .players_mut()
+                .add_directed_server_chat_message(msg, admin_player_id);
+            server.audit(admin_player_id, "mode", arg, None, Err("invalid setting"));
+            return;
+        };
+        self.current_name = name.clone();
+        self.current = mode;
+
+        self.current.init(server.rb_mut());
+        server.new_game(self.current.get_initial_game_values());
+
+        info!(
+            "{} ({}) changed game mode to {}",
+            admin_player_name, admin_player_id, arg
+        );
+        let msg = format!("Game mode changed to {} by {}", arg, admin_player_name);
+        server.players_mut().add_server_chat_message(msg);
+    }
+}
+
+impl GameMode for SwitchableGameMode {
+    fn init(&mut self, server: ServerMut) {
+        self.current.init(server)
+    }
+
+    fn before_tick(&mut self, server: ServerMut) {
+        self.current.before_tick(server)
+    }
+
+    fn after_tick(&mut self, server: ServerMut, events: &[PhysicsEvent]) {
+        self.current.after_tick(server, events)
+    }
+
+    fn handle_command(&mut self, server: ServerMut, cmd: &str, arg: &str, player_id: PlayerId) {
+        if cmd == "mode" {
+            self.change_mode(server, arg, player_id);
+        } else {
+            self.current.handle_command(server, cmd, arg, player_id)
+        }
+    }
+
+    fn get_initial_game_values(&mut self) -> InitialGameValues {
+        self.current.get_initial_game_values()
+    }
+
+    fn game_started(&mut self, server: ServerMut) {
+        self.current.game_started(server)
+    }
+
+    fn before_player_exit(&mut self, server: ServerMut, player_id: PlayerId, reason: ExitReason) {
+        self.current.before_player_exit(server, player_id, reason)
+    }
+
+    fn after_player_join(&mut self, server: ServerMut, player_index: PlayerId) {
+        self.current.after_player_join(server, player_index)
+    }
+
+    fn server_list_team_size(&self) -> u32 {
+        self.current.server_list_team_size()
+    }
+
+    fn include_tick_in_recording(&self, server: Server) -> bool {
+        self.current.include_tick_in_recording(server)
+    }
+
+    fn supports_vote(&self, vote: GameVote) -> bool {
+        self.current.supports_vote(vote)
+    }
+
+    fn vote_passed(&mut self, server: ServerMut, vote: GameVote) {
+        self.current.vote_passed(server, vote)
+    }
+}
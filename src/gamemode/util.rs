@@ -1,5 +1,6 @@
 use crate::game::{PlayerId, Rink, Team};
 use crate::gamemode::ServerPlayersMut;
+use crate::server::{TeamRequest, TeamRequestSource};
 use nalgebra::{Point3, Rotation3};
 use smallvec::SmallVec;
 use std::collections::{HashMap, HashSet};
@@ -7,6 +8,9 @@ use std::f32::consts::{FRAC_PI_2, PI};
 use std::rc::Rc;
 use tracing::info;
 
+/// Players joining a team, with the /team request that moved them there, if any.
+type JoiningPlayers = SmallVec<[(PlayerId, Rc<str>, Option<TeamRequest>); 32]>;
+
 pub fn add_players<
     F1: Fn(Team, usize) -> (Point3<f32>, Rotation3<f32>),
     FSpectate: FnMut(PlayerId) -> (),
@@ -23,19 +27,53 @@ pub fn add_players<
     let mut red_player_count = 0;
     let mut blue_player_count = 0;
     let mut spectating_players = SmallVec::<[_; 32]>::new();
-    let mut joining_red = SmallVec::<[_; 32]>::new();
-    let mut joining_blue = SmallVec::<[_; 32]>::new();
-    for player in server.iter() {
+    let mut joining_red = JoiningPlayers::new();
+    let mut joining_blue = JoiningPlayers::new();
+    let mut moved_red = JoiningPlayers::new();
+    let mut moved_blue = JoiningPlayers::new();
+    let mut refused = SmallVec::<[_; 32]>::new();
+    let mut refused_moves = SmallVec::<[_; 8]>::new();
+    let teams_locked = server.teams_locked();
+    let (red_on_ice, blue_on_ice) = server.count_team_members();
+    for mut player in server.iter_mut() {
         let player_id = player.id;
-        let input = player.input();
         let team = player.team();
         team_switch_timer
             .get_mut(&player_id)
             .map(|x| *x = x.saturating_sub(1));
+        // Requested team changes skip the team lock and the team switch timer
+        if let Some(request) = player
+            .take_team_request()
+            .filter(|request| request.team != team)
+        {
+            // Players that leave this tick are still counted, so an accepted move always fits
+            let full = match request.team {
+                Some(Team::Red) => red_on_ice + moved_red.len() >= team_max,
+                Some(Team::Blue) => blue_on_ice + moved_blue.len() >= team_max,
+                None => false,
+            };
+            if full {
+                refused_moves.push((player.name(), request));
+            } else {
+                if team.is_some() {
+                    if request.team.is_none() {
+                        team_switch_timer.insert(player_id, 500);
+                    }
+                    spectating_players.push((player_id, player.name(), Some(request.clone())));
+                }
+                match request.team {
+                    Some(Team::Red) => moved_red.push((player_id, player.name(), Some(request))),
+                    Some(Team::Blue) => moved_blue.push((player_id, player.name(), Some(request))),
+                    None => {}
+                }
+                continue;
+            }
+        }
+        let input = player.input();
         if let Some(team) = team {
             if input.spectate() {
                 team_switch_timer.insert(player_id, 500);
-                spectating_players.push((player_id, player.name()))
+                spectating_players.push((player_id, player.name(), None))
             } else if team == Team::Red {
                 red_player_count += 1;
             } else {
@@ -45,18 +83,67 @@ pub fn add_players<
             if (input.join_red() || input.join_blue())
                 && team_switch_timer.get(&player_id).map_or(true, |x| *x == 0)
             {
-                if input.join_red() {
-                    joining_red.push((player_id, player.name()));
+                if teams_locked {
+                    team_switch_timer.insert(player_id, 500);
+                    refused.push(player_id);
+                } else if input.join_red() {
+                    joining_red.push((player_id, player.name(), None));
                 } else if input.join_blue() {
-                    joining_blue.push((player_id, player.name()));
+                    joining_blue.push((player_id, player.name(), None));
                 }
             }
         }
     }
-    for (player_id, player_name) in spectating_players {
+    for player_id in refused {
+        server.add_directed_server_chat_message(
+            "Teams are locked, ask an administrator to join a team",
+            player_id,
+        );
+    }
+    for (player_name, request) in refused_moves {
+        if let (Some(team), TeamRequestSource::Admin { player_id, .. }) =
+            (request.team, request.source)
+        {
+            let msg = format!("{} could not join team {}, it is full", player_name, team);
+            server.add_directed_server_chat_message(msg, player_id);
+        }
+    }
+    for (player_id, player_name, request) in spectating_players {
         info!("{} ({}) is spectating", player_name, player_id);
         server.move_to_spectator(player_id);
         on_spectate(player_id);
+        match request {
+            Some(TeamRequest {
+                team: None,
+                source:
+                    TeamRequestSource::Admin {
+                        player_id: admin_player_id,
+                        player_name: admin_player_name,
+                    },
+            }) => {
+                info!(
+                    "{} ({}) moved {} ({}) to spectators",
+                    admin_player_name, admin_player_id, player_name, player_id
+                );
+                let msg = format!(
+                    "{} moved to spectators by {}",
+                    player_name, admin_player_name
+                );
+                server.add_server_chat_message(msg);
+            }
+            Some(TeamRequest {
+                team: None,
+                source: TeamRequestSource::Afk,
+            }) => {
+                info!(
+                    "{} ({}) moved to spectators for being AFK",
+                    player_name, player_id
+                );
+                let msg = format!("{} moved to spectators for being AFK", player_name);
+                server.add_server_chat_message(msg);
+            }
+            _ => {}
+        }
         if let Some(show_extra_messages) = show_extra_messages {
             let s = format!("{} is spectating", player_name);
             for i in show_extra_messages.iter() {
@@ -65,35 +152,53 @@ pub fn add_players<
         }
     }
 
-    let mut add_players =
-        |players: SmallVec<[(PlayerId, Rc<str>); 32]>, team: Team, player_count: &mut usize| {
-            for (i, (player_id, player_name)) in players.into_iter().enumerate() {
-                if *player_count >= team_max {
-                    break;
-                }
-
+    let mut add_players = |players: JoiningPlayers, team: Team, player_count: &mut usize| {
+        for (i, (player_id, player_name, request)) in players.into_iter().enumerate() {
+            let res = *player_count < team_max && {
                 let (pos, rot) = coords(team, i);
+                server.spawn_skater(player_id, team, pos, rot, false)
+            };
 
-                let res = server.spawn_skater(player_id, team, pos, rot, false);
-
-                if res {
-                    info!("{} ({}) has joined team {:?}", player_name, player_id, team);
-                    *player_count += 1;
-                    on_join(player_id, team);
-                    if let Some(show_extra_messages) = show_extra_messages {
-                        let s = format!("{} is playing for Red", player_name);
-                        for msg_player_id in show_extra_messages.iter() {
-                            server.add_directed_server_chat_message(s.clone(), *msg_player_id);
-                        }
+            if res {
+                info!("{} ({}) has joined team {:?}", player_name, player_id, team);
+                *player_count += 1;
+                on_join(player_id, team);
+                if let Some(TeamRequestSource::Admin {
+                    player_id: admin_player_id,
+                    player_name: admin_player_name,
+                }) = request.map(|request| request.source)
+                {
+                    info!(
+                        "{} ({}) moved {} ({}) to team {:?}",
+                        admin_player_name, admin_player_id, player_name, player_id, team
+                    );
+                    let msg = format!(
+                        "{} moved to team {} by {}",
+                        player_name, team, admin_player_name
+                    );
+                    server.add_server_chat_message(msg);
+                }
+                if let Some(show_extra_messages) = show_extra_messages {
+                    let s = format!("{} is playing for Red", player_name);
+                    for msg_player_id in show_extra_messages.iter() {
+                        server.add_directed_server_chat_message(s.clone(), *msg_player_id);
                     }
-                } else {
-                    break;
                 }
+            } else if let Some(TeamRequestSource::Admin {
+                player_id: admin_player_id,
+                ..
+            }) = request.map(|request| request.source)
+            {
+                let msg = format!("{} could not join team {}", player_name, team);
+                server.add_directed_server_chat_message(msg, admin_player_id);
             }
-        };
+        }
+    };
 
-    add_players(joining_red, Team::Red, &mut red_player_count);
-    add_players(joining_blue, Team::Blue, &mut blue_player_count);
+    moved_red.extend(joining_red);
+    moved_blue.extend(joining_blue);
+    add_players(moved_red, Team::Red, &mut red_player_count);
+    add_players(moved_blue, Team::Blue, &mut blue_player_count);
 
     (red_player_count, blue_player_count)
 }
@@ -140,3 +245,171 @@ pub fn get_spawnpoint(
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{PlayerId, PlayerIndex, PlayerInput, Team};
+    use crate::gamemode::util::add_players;
+    use crate::gamemode::ServerPlayersMut;
+    use crate::server::{
+        HQMMessage, HQMServerPlayersAndMessages, PlayerListExt, ServerPlayerData, TeamRequest,
+        TeamRequestSource,
+    };
+    use nalgebra::{Point3, Rotation3};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    fn add_spectators(state: &mut HQMServerPlayersAndMessages, count: u16) -> Vec<PlayerId> {
+        (0..count)
+            .map(|i| {
+                let addr = SocketAddr::from(([10, 0, 0, 1], 10000 + i));
+                state.add_player(&format!("Player {}", i), addr).unwrap()
+            })
+            .collect()
+    }
+
+    fn spawn(state: &mut HQMServerPlayersAndMessages, player_id: PlayerId, team: Team) {
+        assert!(state.spawn_skater(
+            player_id,
+            team,
+            Point3::origin(),
+            Rotation3::identity(),
+            false
+        ));
+    }
+
+    fn team(state: &HQMServerPlayersAndMessages, player_id: PlayerId) -> Option<Team> {
+        let player = state.players.get_player(player_id).unwrap();
+        player.object.as_ref().map(|(_, _, team)| *team)
+    }
+
+    fn admin_request(team: Option<Team>, admin_player_id: PlayerId) -> Option<TeamRequest> {
+        Some(TeamRequest {
+            team,
+            source: TeamRequestSource::Admin {
+                player_id: admin_player_id,
+                player_name: "Admin".into(),
+            },
+        })
+    }
+
+    fn chat_messages(state: &HQMServerPlayersAndMessages, player_id: PlayerId) -> Vec<String> {
+        match &state.players.get_player(player_id).unwrap().data {
+            ServerPlayerData::NetworkPlayer { data } => data
+                .messages
+                .iter()
+                .filter_map(|x| match &**x {
+                    HQMMessage::Chat { message, .. } => Some(message.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn run(
+        state: &mut HQMServerPlayersAndMessages,
+        team_max: usize,
+        team_switch_timer: &mut HashMap<PlayerId, u32>,
+    ) -> (usize, usize) {
+        add_players(
+            ServerPlayersMut { state },
+            team_max,
+            team_switch_timer,
+            None,
+            |_, _| (Point3::origin(), Rotation3::identity()),
+            |_| {},
+            |_, _| {},
+        )
+    }
+
+    #[test]
+    fn team_requests() {
+        let mut state = HQMServerPlayersAndMessages::new(1);
+        let players = add_spectators(&mut state, 6);
+        let admin = players[5];
+        spawn(&mut state, players[0], Team::Red);
+        spawn(&mut state, players[1], Team::Blue);
+        spawn(&mut state, players[4], Team::Blue);
+        state.teams_locked = true;
+
+        let request = |state: &mut HQMServerPlayersAndMessages, i: usize, request| {
+            state
+                .players
+                .get_player_mut(players[i])
+                .unwrap()
+                .team_request = request;
+        };
+        // Moved by an administrator despite the team lock
+        request(&mut state, 2, admin_request(Some(Team::Red), admin));
+        request(
+            &mut state,
+            0,
+            Some(TeamRequest {
+                team: None,
+                source: TeamRequestSource::Afk,
+            }),
+        );
+        // Team blue is full
+        request(&mut state, 3, admin_request(Some(Team::Blue), admin));
+        // Joining by the player's own input is refused while teams are locked
+        state.players.get_player_mut(admin).unwrap().input = PlayerInput {
+            keys: 0x8,
+            ..Default::default()
+        };
+
+        let mut team_switch_timer = HashMap::new();
+        assert_eq!(run(&mut state, 2, &mut team_switch_timer), (1, 2));
+        assert_eq!(team(&state, players[0]), None);
+        assert_eq!(team(&state, players[1]), Some(Team::Blue));
+        assert_eq!(team(&state, players[2]), Some(Team::Red));
+        assert_eq!(team(&state, players[3]), None);
+        assert_eq!(team(&state, admin), None);
+        assert_eq!(team_switch_timer.get(&players[0]), Some(&500));
+        assert_eq!(team_switch_timer.get(&admin), Some(&500));
+
+        let messages = chat_messages(&state, admin);
+        for msg in [
+            "Player 0 moved to spectators for being AFK",
+            "Player 2 moved to team Red by Admin",
+            "Player 3 could not join team Blue, it is full",
+            "Teams are locked, ask an administrator to join a team",
+        ] {
+            assert!(messages.iter().any(|x| x == msg), "{}", msg);
+        }
+        // Requests are used once
+        assert!(state
+            .players
+            .iter_players()
+            .all(|(_, player)| player.team_request.is_none()));
+    }
+
+    #[test]
+    fn team_request_without_object_slots() {
+        // Only two object slots are left for skaters
+        let mut state = HQMServerPlayersAndMessages::new(30);
+        let players = add_spectators(&mut state, 5);
+        let admin = players[4];
+        spawn(&mut state, players[0], Team::Red);
+        spawn(&mut state, players[1], Team::Blue);
+        for i in [2, 3] {
+            state
+                .players
+                .get_player_mut(players[i])
+                .unwrap()
+                .team_request = admin_request(Some(Team::Red), admin);
+        }
+
+        assert_eq!(run(&mut state, 5, &mut HashMap::new()), (1, 1));
+        // Every player that could not be spawned is reported, not only the first one
+        let messages = chat_messages(&state, admin);
+        for msg in [
+            "Player 2 could not join team Red",
+            "Player 3 could not join team Red",
+        ] {
+            assert!(messages.iter().any(|x| x == msg), "{}", msg);
+        }
+        let (_, player) = state.players.get_player_by_index(PlayerIndex(2)).unwrap();
+        assert!(player.object.is_none());
+    }
+}
@@ -1,9 +1,16 @@
+use crate::admin::AdminPermission;
+use crate::admin_commands::{faceoff_spot_position, is_inside_rink, TeleportTarget};
 use crate::game::Puck;
 use crate::game::{PhysicsEvent, PlayerId};
 use crate::gamemode::util::{add_players, get_spawnpoint, SpawnPoint};
 use crate::gamemode::{GameMode, InitialGameValues, PuckExt, ServerMut, ServerMutParts};
 use nalgebra::{Point3, Rotation3};
+use reborrow::ReborrowMut;
 use std::collections::HashMap;
+use tracing::info;
+
+/// Pucks and players share 32 object slots, so leave room for some players.
+pub const MAX_PUCKS: usize = 24;
 
 pub struct PermanentWarmup {
     pucks: usize,
@@ -19,6 +26,131 @@ impl PermanentWarmup {
             team_switch_timer: Default::default(),
         }
     }
+    fn spawn_pucks(&self, mut server: ServerMut) {
+        let warmup_pucks = self.pucks;
+        let rink = server.rink();
+        let width = rink.width;
+        let length = rink.length;
+        let puck_line_start = width / 2.0 - 0.4 * ((warmup_pucks as f32) - 1.0);
+
+        for i in 0..warmup_pucks {
+            let pos = Point3::new(puck_line_start + 0.8 * (i as f32), 1.5, length / 2.0);
+            let rot = Rotation3::identity();
+            server.pucks_mut().spawn_puck(Puck::new(pos, rot));
+        }
+    }
+
+    fn set_pucks(&mut self, mut server: ServerMut, admin_player_id: PlayerId, pucks: usize) {
+        let Some(admin_player_name) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+            .map(|player| player.name())
+        else {
+            return;
+        };
+        if pucks == 0 || pucks > MAX_PUCKS {
+            let msg = format!("Number of pucks must be between 1 and {}", MAX_PUCKS);
+            server
+                .players_mut()
+                .add_directed_server_chat_message(msg, admin_player_id);
+            return;
+        }
+        if !server.add_puck_slots(pucks) {
+            server.players_mut().add_directed_server_chat_message(
+                "Not enough free object slots for that many pucks",
+                admin_player_id,
+            );
+            server.audit(
+                admin_player_id,
+                "pucks",
+                &pucks.to_string(),
+                None,
+                Err("no free object slots"),
+            );
+            return;
+        }
+        self.pucks = pucks;
+        server.pucks_mut().remove_all_pucks();
+        self.spawn_pucks(server.rb_mut());
+        info!(
+            "{} ({}) set number of pucks to {}",
+            admin_player_name, admin_player_id, pucks
+        );
+        let msg = format!("Number of pucks set to {} by {}", pucks, admin_player_name);
+        server.players_mut().add_server_chat_message(msg);
+    }
+
+    fn clear_pucks(&mut self, mut server: ServerMut, admin_player_id: PlayerId) {
+        let Some(admin_player_name) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+            .map(|player| player.name())
+        else {
+            return;
+        };
+        server.pucks_mut().remove_all_pucks();
+        info!(
+            "{} ({}) removed all pucks",
+            admin_player_name, admin_player_id
+        );
+        let msg = format!("Pucks removed by {}", admin_player_name);
+        server.players_mut().add_server_chat_message(msg);
+    }
+
+    /// Spawns a puck at a player's stick, at a faceoff spot or at coordinates in meters.
+    fn place_puck(&mut self, mut server: ServerMut, admin_player_id: PlayerId, arg: &str) {
+        let Some(admin_player_name) = server
+            .players_mut()
+            .check_permission_or_deny(admin_player_id, AdminPermission::Game)
+            .map(|player| player.name())
+        else {
+            return;
+        };
+        let rink = server.rink();
+        let pos = match arg.parse::<TeleportTarget>() {
+            Ok(TeleportTarget::Player(player_index)) => server
+                .players()
+                .get_by_index(player_index)
+                .and_then(|player| player.skater().map(|(_, skater)| skater.stick_pos)),
+            Ok(TeleportTarget::FaceoffSpot(spot)) => Some(faceoff_spot_position(rink, spot)),
+            Ok(TeleportTarget::Position(pos)) => Some(pos).filter(|pos| is_inside_rink(rink, pos)),
+            Err(()) => None,
+        };
+        let Some(pos) = pos else {
+            server.players_mut().add_directed_server_chat_message(
+                "Unknown player, faceoff spot or position",
+                admin_player_id,
+            );
+            return;
+        };
+        if server
+            .pucks_mut()
+            .spawn_puck(Puck::new(pos, Rotation3::identity()))
+            .is_none()
+        {
+            server.players_mut().add_directed_server_chat_message(
+                "No free puck slots, use /pucks to add more",
+                admin_player_id,
+            );
+            server.audit(
+                admin_player_id,
+                "puck",
+                arg,
+                None,
+                Err("no free puck slots"),
+            );
+            return;
+        }
+        info!(
+            "{} ({}) spawned a puck at {}",
+            admin_player_name, admin_player_id, arg
+        );
+        let msg = format!("Puck placed at {}", arg);
+        server
+            .players_mut()
+            .add_directed_server_chat_message(msg, admin_player_id);
+    }
+
     fn update_players(&mut self, mut server: ServerMut) {
         let spawn_point = self.spawn_point;
         let ServerMutParts { players, rink, .. } = server.as_mut_parts();
@@ -44,13 +176,21 @@ impl GameMode for PermanentWarmup {
         // Nothing
     }
 
-    fn handle_command(
-        &mut self,
-        _server: ServerMut,
-        _cmd: &str,
-        _arg: &str,
-        _player_index: PlayerId,
-    ) {
+    fn handle_command(&mut self, server: ServerMut, cmd: &str, arg: &str, player_id: PlayerId) {
+        match cmd {
+            "pucks" => {
+                if let Ok(pucks) = arg.parse::<usize>() {
+                    self.set_pucks(server, player_id, pucks);
+                }
+            }
+            "clearpucks" => {
+                self.clear_pucks(server, player_id);
+            }
+            "puck" => {
+                self.place_puck(server, player_id, arg);
+            }
+            _ => {}
+        }
     }
 
     fn get_initial_game_values(&mut self) -> InitialGameValues {
@@ -62,18 +202,8 @@ impl GameMode for PermanentWarmup {
         }
     }
 
-    fn game_started(&mut self, mut server: ServerMut) {
-        let warmup_pucks = self.pucks;
-        let rink = server.rink();
-        let width = rink.width;
-        let length = rink.length;
-        let puck_line_start = width / 2.0 - 0.4 * ((warmup_pucks - 1) as f32);
-
-        for i in 0..warmup_pucks {
-            let pos = Point3::new(puck_line_start + 0.8 * (i as f32), 1.5, length / 2.0);
-            let rot = Rotation3::identity();
-            server.pucks_mut().spawn_puck(Puck::new(pos, rot));
-        }
+    fn game_started(&mut self, server: ServerMut) {
+        self.spawn_pucks(server);
     }
 
     fn server_list_team_size(&self) -> u32 {
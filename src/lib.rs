@@ -1,16 +1,41 @@
+pub mod admin;
 mod admin_commands;
+pub mod afk;
+pub mod allowlist;
+pub mod announcement;
 
 pub mod gamemode;
 
 pub mod ban;
+pub mod console;
+pub mod flood;
 pub mod game;
+pub mod names;
 pub mod physics;
 mod protocol;
 pub mod record;
+pub mod restart;
 mod server;
+pub mod slots;
+pub mod vote;
+pub mod warning;
 
 pub use server::run_server;
 
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::admin::AdminAccount;
+use crate::afk::AfkConfiguration;
+use crate::announcement::AnnouncementConfiguration;
+use crate::console::ConsoleReceiver;
+use crate::flood::FloodConfiguration;
+use crate::names::NamePolicyConfiguration;
+use crate::restart::RestartMethod;
+use crate::slots::SlotConfiguration;
+use crate::vote::VoteConfiguration;
+use crate::warning::WarningConfiguration;
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum ReplayRecording {
     Off,
@@ -22,9 +47,30 @@ pub enum ReplayRecording {
 pub struct ServerConfiguration {
     pub welcome: Vec<String>,
     pub password: Option<String>,
+    pub admins: Vec<AdminAccount>,
     pub player_max: usize,
+    /// If set, the server is private and only players on the allowlist in this file can join.
+    pub allowlist_file: Option<PathBuf>,
+    /// File with player name pattern bans. If not set, name bans are kept in memory.
+    pub name_ban_file: Option<PathBuf>,
+    pub names: NamePolicyConfiguration,
+    pub slots: SlotConfiguration,
+    pub flood: FloodConfiguration,
 
     pub recording_enabled: ReplayRecording,
     pub server_name: String,
     pub server_service: Option<String>,
+    /// Console that sends commands to the server, created with [console_channel](crate::console::console_channel).
+    pub console: Option<ConsoleReceiver>,
+    /// How /serverrestart restarts the server, or [None] if it is disabled.
+    pub restart_method: Option<RestartMethod>,
+    /// Default countdown before /serverrestart restarts the server.
+    pub restart_countdown: Duration,
+    /// Directory with physics presets that can be loaded with /physics preset.
+    pub physics_preset_directory: PathBuf,
+
+    pub vote: VoteConfiguration,
+    pub announcements: AnnouncementConfiguration,
+    pub warnings: WarningConfiguration,
+    pub afk: AfkConfiguration,
 }
@@ -1,405 +1,1129 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 // INI Crate For configuration
 use ini::Ini;
 use std::env;
+use std::io::IsTerminal;
 
-use ini::Properties;
-use migo_hqm_server::ban::{BanCheck, FileBanCheck, InMemoryBanCheck};
+use migo_hqm_server::admin::{AdminAccount, AdminPermissions, AdminRole};
+use migo_hqm_server::afk::AfkConfiguration;
+use migo_hqm_server::announcement::{
+    AnnouncementConfiguration, DailyAnnouncement, RepeatedAnnouncement, ScheduledTime,
+};
+use migo_hqm_server::ban::{
+    BanCheck, BanCheckFallback, ExternalBanCheck, FileBanCheck, HttpBanCheckRequests,
+    InMemoryBanCheck, LayeredBanCheck,
+};
+use migo_hqm_server::console::console_channel;
+use migo_hqm_server::flood::FloodConfiguration;
 use migo_hqm_server::game::PhysicsConfiguration;
 use migo_hqm_server::gamemode::russian::RussianGameMode;
 use migo_hqm_server::gamemode::shootout::ShootoutGameMode;
 use migo_hqm_server::gamemode::standard_match::{
     IcingConfiguration, MatchConfiguration, OffsideConfiguration, OffsideLineConfiguration,
-    StandardMatchGameMode, TwoLinePassConfiguration,
+    StandardMatchGameMode, TwoLinePassConfiguration, MAX_TEAM_SIZE,
 };
+use migo_hqm_server::gamemode::switch::{GameModeFactory, SwitchableGameMode};
 use migo_hqm_server::gamemode::util::SpawnPoint;
-use migo_hqm_server::gamemode::warmup::PermanentWarmup;
+use migo_hqm_server::gamemode::warmup::{PermanentWarmup, MAX_PUCKS};
+use migo_hqm_server::names::NamePolicyConfiguration;
 use migo_hqm_server::record::{
     RecordingSaveMethod, RecordingSaveToFile, RecordingSendToHttpEndpoint,
 };
+use migo_hqm_server::restart::RestartMethod;
+use migo_hqm_server::slots::SlotConfiguration;
+use migo_hqm_server::vote::VoteConfiguration;
+use migo_hqm_server::warning::WarningConfiguration;
 use migo_hqm_server::{ReplayRecording, ServerConfiguration};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
 use tracing_appender;
 use tracing_subscriber;
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Parses the optional number given to /mode, using the configured value if none is given.
+/// Returns [None] if the number is invalid or outside of `range`.
+fn parse_mode_arg<T: FromStr + PartialOrd>(
+    arg: &str,
+    default: T,
+    range: impl RangeBounds<T>,
+) -> Option<T> {
+    if arg.is_empty() {
+        Some(default)
+    } else {
+        arg.parse::<T>().ok().filter(|x| range.contains(x))
+    }
+}
 
-enum HQMServerMode {
-    Match,
-    PermanentWarmup,
-    Russian,
-    Shootout,
+/// Reads values from the configuration file, collecting errors for invalid values and warnings
+/// for unknown keys and values, so that all problems can be reported at once.
+struct ConfigReader<'a> {
+    conf: &'a Ini,
+    /// Keys that have been read, as (section, key).
+    known: HashSet<(String, String)>,
+    errors: Vec<String>,
+    warnings: Vec<String>,
 }
 
-fn is_true(s: &str) -> bool {
-    s.eq_ignore_ascii_case("true") || s.eq_ignore_ascii_case("on")
+impl<'a> ConfigReader<'a> {
+    fn new(conf: &'a Ini) -> Self {
+        Self {
+            conf,
+            known: HashSet::new(),
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    fn error(&mut self, section: &str, key: &str, msg: impl Display) {
+        self.errors.push(format!("[{}] {}: {}", section, key, msg));
+    }
+
+    fn warning(&mut self, section: &str, key: &str, msg: impl Display) {
+        self.warnings
+            .push(format!("[{}] {}: {}", section, key, msg));
+    }
+
+    fn get_str(&mut self, section: &str, key: &str) -> Option<&'a str> {
+        self.known.insert((section.to_owned(), key.to_owned()));
+        self.conf.section(Some(section)).and_then(|x| x.get(key))
+    }
+
+    /// Returns all values of a key that can be repeated.
+    fn get_all(&mut self, section: &str, key: &str) -> Vec<&'a str> {
+        self.known.insert((section.to_owned(), key.to_owned()));
+        self.conf
+            .section(Some(section))
+            .map_or(vec![], |x| x.get_all(key).collect())
+    }
+
+    fn parse<T: FromStr>(
+        &mut self,
+        section: &str,
+        key: &str,
+        value: &str,
+        expected: &str,
+    ) -> Option<T> {
+        let res = value.trim().parse::<T>().ok();
+        if res.is_none() {
+            self.error(
+                section,
+                key,
+                format!("expected {}, found \"{}\"", expected, value),
+            );
+        }
+        res
+    }
+
+    /// Returns the parsed value, or [None] if the key is missing or the value is invalid.
+    fn get_optional<T: FromStr>(&mut self, section: &str, key: &str, expected: &str) -> Option<T> {
+        let value = self.get_str(section, key)?;
+        self.parse(section, key, value, expected)
+    }
+
+    fn get<T: FromStr>(&mut self, section: &str, key: &str, default: T, expected: &str) -> T {
+        self.get_optional(section, key, expected).unwrap_or(default)
+    }
+
+    fn require<T: FromStr + Default>(&mut self, section: &str, key: &str, expected: &str) -> T {
+        if self.get_str(section, key).is_none() {
+            self.error(section, key, format!("missing, expected {}", expected));
+        }
+        self.get_optional(section, key, expected)
+            .unwrap_or_default()
+    }
+
+    /// Reports an error if a configured value is outside of `min..=max`.
+    fn check_range<T: PartialOrd + Display>(
+        &mut self,
+        section: &str,
+        key: &str,
+        value: &T,
+        min: T,
+        max: T,
+    ) {
+        let range = min..=max;
+        if self.get_str(section, key).is_some() && !range.contains(value) {
+            self.error(
+                section,
+                key,
+                format!(
+                    "expected a number from {} to {}, found {}",
+                    range.start(),
+                    range.end(),
+                    value
+                ),
+            );
+        }
+    }
+
+    fn get_seconds(&mut self, section: &str, key: &str, default: Duration) -> Duration {
+        self.get_optional(section, key, "a number of seconds")
+            .map_or(default, Duration::from_secs)
+    }
+
+    fn get_minutes(&mut self, section: &str, key: &str, default: Duration) -> Duration {
+        let Some(minutes) = self.get_optional::<u64>(section, key, "a number of minutes") else {
+            return default;
+        };
+        match minutes.checked_mul(60) {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => {
+                self.error(section, key, format!("{} minutes is too long", minutes));
+                default
+            }
+        }
+    }
+
+    /// Returns the value of a key with a fixed set of values. Unknown values are reported as a
+    /// warning, and the default is used instead.
+    fn get_enum<T: Copy>(
+        &mut self,
+        section: &str,
+        key: &str,
+        default: &str,
+        values: &[(&str, T)],
+    ) -> T {
+        let find = |s: &str| {
+            values
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()))
+                .map(|(_, x)| *x)
+        };
+        let default_value = find(default).unwrap();
+        let Some(value) = self.get_str(section, key) else {
+            return default_value;
+        };
+        find(value).unwrap_or_else(|| {
+            let names: Vec<&str> = values.iter().map(|(name, _)| *name).collect();
+            self.warning(
+                section,
+                key,
+                format!(
+                    "unknown value \"{}\", expected one of {}, using {}",
+                    value,
+                    names.join(", "),
+                    default
+                ),
+            );
+            default_value
+        })
+    }
+
+    fn get_bool(&mut self, section: &str, key: &str, default: bool) -> bool {
+        let values = [
+            ("true", true),
+            ("on", true),
+            ("false", false),
+            ("off", false),
+        ];
+        self.get_enum(
+            section,
+            key,
+            if default { "true" } else { "false" },
+            &values,
+        )
+    }
+
+    /// Returns the values of a comma-separated list.
+    fn get_list<T: FromStr>(&mut self, section: &str, key: &str, expected: &str) -> Vec<T> {
+        let Some(value) = self.get_str(section, key) else {
+            return vec![];
+        };
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .filter_map(|x| self.parse(section, key, x, expected))
+            .collect()
+    }
+
+    /// Warns about keys that were never read. Keys in `any_key_sections` are names chosen by the user.
+    fn warn_unknown_keys(&mut self, any_key_sections: &[&str]) {
+        let conf = self.conf;
+        for (section, properties) in conf.iter() {
+            let Some(section) = section else {
+                for (key, _) in properties.iter() {
+                    self.warnings
+                        .push(format!("{}: key outside of a section is ignored", key));
+                }
+                continue;
+            };
+            if any_key_sections.contains(&section) {
+                continue;
+            }
+            if !self.known.iter().any(|(x, _)| x == section) {
+                self.warnings
+                    .push(format!("[{}]: unknown section", section));
+                continue;
+            }
+            for (key, _) in properties.iter() {
+                if !self.known.contains(&(section.to_owned(), key.to_owned())) {
+                    self.warning(section, key, "unknown key");
+                }
+            }
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut config_path = "config.ini".to_owned();
+    let mut check_config = false;
+    for arg in env::args().skip(1) {
+        if arg == "--check-config" {
+            check_config = true;
+        } else {
+            config_path = arg;
+        }
+    }
+
+    let conf = match Ini::load_from_file(&config_path) {
+        Ok(conf) => conf,
+        Err(e) => {
+            eprintln!("Could not open configuration file {}: {}", config_path, e);
+            std::process::exit(1);
+        }
+    };
+    let mut reader = ConfigReader::new(&conf);
+
+    // Server information
+    if conf.section(Some("Server")).is_none() {
+        eprintln!("Configuration file {} has no [Server] section", config_path);
+        std::process::exit(1);
+    }
+    let server_name: String = reader.require("Server", "name", "a server name");
+    let server_port: u16 = reader.require("Server", "port", "a port number");
+    if reader.get_str("Server", "public").is_none() {
+        reader.error("Server", "public", "missing, expected true or false");
+    }
+    let server_public = reader.get_bool("Server", "public", false);
+    let public_address = reader
+        .get_str("Server", "public_address")
+        .unwrap_or("https://sam2.github.io/HQMMasterServerEndpoint/");
+    let public_address = server_public.then_some(public_address);
+    let server_player_max: usize = reader.require("Server", "player_max", "a number");
+    let server_team_max: usize = reader.require("Server", "team_max", "a number");
+    reader.check_range("Server", "team_max", &server_team_max, 1, MAX_TEAM_SIZE);
+
+    let server_password = reader.get_str("Server", "password").map(|x| x.to_string());
+    let mode = reader.get_enum(
+        "Server",
+        "mode",
+        "match",
+        &[
+            ("match", "match"),
+            ("warmup", "warmup"),
+            ("russian", "russian"),
+            ("shootout", "shootout"),
+        ],
+    );
+
+    let replays_enabled = reader.get_enum(
+        "Server",
+        "replays",
+        "off",
+        &[
+            ("off", ReplayRecording::Off),
+            ("false", ReplayRecording::Off),
+            ("on", ReplayRecording::On),
+            ("true", ReplayRecording::On),
+            ("standby", ReplayRecording::Standby),
+        ],
+    );
 
-    let config_path = if args.len() > 1 {
-        &args[1]
+    let log_name = reader
+        .get_str("Server", "log_name")
+        .map_or(format!("{}.log", server_name), |x| String::from(x));
+    let audit_log_name = reader
+        .get_str("Server", "audit_log_name")
+        .map_or(format!("{}-audit.log", server_name), |x| String::from(x));
+
+    let welcome = reader.get_str("Server", "welcome").unwrap_or("");
+
+    let welcome_str = welcome
+        .lines()
+        .map(String::from)
+        .filter(|x| !x.is_empty())
+        .collect();
+
+    let replay_endpoint = reader.get_str("Server", "replay_endpoint");
+    let replay_directory = reader
+        .get_str("Server", "replay_directory")
+        .map_or(PathBuf::from("replays"), PathBuf::from);
+    let replay_saving: Box<dyn RecordingSaveMethod> = if let Some(url) = replay_endpoint {
+        Box::new(RecordingSendToHttpEndpoint::new(url.to_string()))
     } else {
-        "config.ini"
+        Box::new(RecordingSaveToFile::new(replay_directory))
     };
 
-    // Load configuration (if exists)
-    if Path::new(config_path).exists() {
-        // Load configuration file
-        let conf = Ini::load_from_file(config_path).unwrap();
-
-        // Server information
-        let server_section = conf.section(Some("Server")).unwrap();
-        let server_name = server_section
-            .get("name")
-            .unwrap()
-            .parse::<String>()
-            .unwrap();
-        let server_port = server_section.get("port").unwrap().parse::<u16>().unwrap();
-        let server_public = is_true(server_section.get("public").unwrap());
-        let public_address = if server_public {
-            Some(
-                server_section
-                    .get("public_address")
-                    .unwrap_or("https://sam2.github.io/HQMMasterServerEndpoint/"),
-            )
+    let server_service = reader.get_str("Server", "service").map(|x| x.to_owned());
+    let restart_exit_code = reader.get("Server", "restart_exit_code", 75, "an exit code");
+    // Without service or restart, /serverrestart is disabled
+    let default_restart = if server_service.is_some() {
+        "systemd"
+    } else {
+        "off"
+    };
+    let restart_method = match reader.get_enum(
+        "Server",
+        "restart",
+        default_restart,
+        &[
+            ("systemd", "systemd"),
+            ("exec", "exec"),
+            ("exit", "exit"),
+            ("off", "off"),
+        ],
+    ) {
+        "systemd" => match &server_service {
+            Some(service) => Some(RestartMethod::Systemd(service.clone())),
+            None => {
+                reader.error("Server", "restart", "systemd requires service to be set");
+                None
+            }
+        },
+        "exec" => Some(RestartMethod::Exec),
+        "exit" => Some(RestartMethod::Exit(restart_exit_code)),
+        _ => None,
+    };
+    let restart_countdown =
+        reader.get_seconds("Server", "restart_countdown", Duration::from_secs(30));
+
+    let physics_preset_directory = reader
+        .get_str("Server", "physics_preset_directory")
+        .map_or(PathBuf::from("physics"), PathBuf::from);
+
+    let ban_file = reader.get_str("Server", "ban_file").map(|x| x.to_owned());
+    let ban_endpoint = reader
+        .get_str("Server", "ban_endpoint")
+        .map(|x| x.to_owned());
+    let ban_endpoint_token = reader
+        .get_str("Server", "ban_endpoint_token")
+        .map(|x| x.to_owned());
+    let ban_endpoint_timeout =
+        reader.get_seconds("Server", "ban_endpoint_timeout", Duration::from_secs(5));
+    let ban_endpoint_fallback = reader.get_enum(
+        "Server",
+        "ban_endpoint_fallback",
+        "allow",
+        &[
+            ("allow", BanCheckFallback::Allow),
+            ("deny", BanCheckFallback::Deny),
+        ],
+    );
+    let ban_lists: Vec<PathBuf> = reader.get_list("Server", "ban_lists", "file paths");
+    for path in &ban_lists {
+        if !path.exists() {
+            reader.error(
+                "Server",
+                "ban_lists",
+                format!("file {} does not exist", path.display()),
+            );
+        }
+    }
+    let ban_primary = reader.get_enum(
+        "Server",
+        "ban_primary",
+        if ban_endpoint.is_some() {
+            "endpoint"
         } else {
-            None
-        };
-        let server_player_max = server_section
-            .get("player_max")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-        let server_team_max = server_section
-            .get("team_max")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
-        let server_password = server_section.get("password").map(|x| x.to_string());
-        let mode = server_section
-            .get("mode")
-            .map_or(HQMServerMode::Match, |x| match x {
-                "warmup" => HQMServerMode::PermanentWarmup,
-                "match" => HQMServerMode::Match,
-                "russian" => HQMServerMode::Russian,
-                "shootout" => HQMServerMode::Shootout,
-                _ => HQMServerMode::Match,
-            });
+            "file"
+        },
+        &[("file", "file"), ("endpoint", "endpoint")],
+    );
+    if ban_file.is_some() || ban_endpoint.is_some() {
+        match ban_primary {
+            "file" if ban_file.is_none() => {
+                reader.error("Server", "ban_primary", "file requires ban_file to be set")
+            }
+            "endpoint" if ban_endpoint.is_none() => reader.error(
+                "Server",
+                "ban_primary",
+                "endpoint requires ban_endpoint to be set",
+            ),
+            _ => {}
+        }
+    }
+    let allowlist_file = reader
+        .get_str("Server", "allowlist_file")
+        .map(PathBuf::from);
+    if let Some(path) = allowlist_file.as_ref().filter(|x| !x.exists()) {
+        reader.error(
+            "Server",
+            "allowlist_file",
+            format!("file {} does not exist", path.display()),
+        );
+    }
+    let name_ban_file = reader.get_str("Server", "name_ban_file").map(PathBuf::from);
 
-        let replays_enabled = match server_section.get("replays") {
-            Some(s) if is_true(s) => ReplayRecording::On,
-            Some(s) if s.eq_ignore_ascii_case("standby") => ReplayRecording::Standby,
-            _ => ReplayRecording::Off,
-        };
+    let rcon_port: Option<u16> = reader.get_optional("Server", "rcon_port", "a port number");
+    let rcon_address = reader.get(
+        "Server",
+        "rcon_address",
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        "an IP address",
+    );
+    let rcon_password = reader
+        .get_str("Server", "rcon_password")
+        .map(|x| x.to_owned());
+    let rcon_socket = reader.get_str("Server", "rcon_socket").map(PathBuf::from);
 
-        let log_name = server_section
-            .get("log_name")
-            .map_or(format!("{}.log", server_name), |x| String::from(x));
+    // Game
+    let limit_jump_speed = reader.get_bool("Game", "limit_jump_speed", false);
 
-        let welcome = server_section.get("welcome").unwrap_or("");
+    // Player votes
+    let vote_defaults = VoteConfiguration::default();
+    let vote = VoteConfiguration {
+        enabled: reader.get_bool("Vote", "enabled", vote_defaults.enabled),
+        kick_threshold: reader.get(
+            "Vote",
+            "kick_threshold",
+            vote_defaults.kick_threshold,
+            "a number",
+        ),
+        mute_threshold: reader.get(
+            "Vote",
+            "mute_threshold",
+            vote_defaults.mute_threshold,
+            "a number",
+        ),
+        reset_threshold: reader.get(
+            "Vote",
+            "reset_threshold",
+            vote_defaults.reset_threshold,
+            "a number",
+        ),
+        start_threshold: reader.get(
+            "Vote",
+            "start_threshold",
+            vote_defaults.start_threshold,
+            "a number",
+        ),
+        min_voters: reader.get("Vote", "min_voters", vote_defaults.min_voters, "a number"),
+        timeout: reader.get_seconds("Vote", "timeout", vote_defaults.timeout),
+        cooldown: reader.get_seconds("Vote", "cooldown", vote_defaults.cooldown),
+    };
+    for (key, value) in [
+        ("kick_threshold", vote.kick_threshold),
+        ("mute_threshold", vote.mute_threshold),
+        ("reset_threshold", vote.reset_threshold),
+        ("start_threshold", vote.start_threshold),
+    ] {
+        reader.check_range("Vote", key, &value, 0.0, 1.0);
+    }
 
-        let welcome_str = welcome
-            .lines()
-            .map(String::from)
-            .filter(|x| !x.is_empty())
-            .collect();
+    // Reserved slots
+    let slot_defaults = SlotConfiguration::default();
+    let slots = SlotConfiguration {
+        reserved_slots: reader.get(
+            "Slots",
+            "reserved_slots",
+            slot_defaults.reserved_slots,
+            "a number",
+        ),
+        reserved_names: reader.get_list("Slots", "reserved_names", "player names"),
+        reserved_ips: reader.get_list("Slots", "reserved_ips", "IP addresses"),
+        spectator_max: reader
+            .get_optional("Slots", "spectator_max", "a number")
+            .or(slot_defaults.spectator_max),
+        spectator_idle_time: reader.get_seconds(
+            "Slots",
+            "spectator_idle_time",
+            slot_defaults.spectator_idle_time,
+        ),
+    };
+
+    // Warnings
+    let warning_defaults = WarningConfiguration::default();
+    let warnings = WarningConfiguration {
+        mute_after: reader.get(
+            "Warnings",
+            "mute_after",
+            warning_defaults.mute_after,
+            "a number",
+        ),
+        kick_after: reader.get(
+            "Warnings",
+            "kick_after",
+            warning_defaults.kick_after,
+            "a number",
+        ),
+        ban_after: reader.get(
+            "Warnings",
+            "ban_after",
+            warning_defaults.ban_after,
+            "a number",
+        ),
+        mute_duration: Some(reader.get_minutes(
+            "Warnings",
+            "mute_minutes",
+            warning_defaults.mute_duration.unwrap_or_default(),
+        ))
+        .filter(|x| !x.is_zero()),
+        ban_duration: reader.get_minutes("Warnings", "ban_minutes", warning_defaults.ban_duration),
+    };
+
+    // AFK players
+    let afk_defaults = AfkConfiguration::default();
+    let afk = AfkConfiguration {
+        warn_after: reader.get_seconds("AFK", "warn_after", afk_defaults.warn_after),
+        spectate_after: reader.get_seconds("AFK", "spectate_after", afk_defaults.spectate_after),
+    };
+
+    // Join limits and flood protection
+    let flood_defaults = FloodConfiguration::default();
+    let flood = FloodConfiguration {
+        max_players_per_ip: reader.get(
+            "Flood",
+            "max_players_per_ip",
+            flood_defaults.max_players_per_ip,
+            "a number",
+        ),
+        max_joins: reader.get("Flood", "max_joins", flood_defaults.max_joins, "a number"),
+        join_interval: reader.get_seconds("Flood", "join_interval", flood_defaults.join_interval),
+        max_packets_per_second: reader.get(
+            "Flood",
+            "max_packets_per_second",
+            flood_defaults.max_packets_per_second,
+            "a number",
+        ),
+        block_time: reader.get_seconds("Flood", "block_time", flood_defaults.block_time),
+    };
 
-        let replay_saving: Box<dyn RecordingSaveMethod> =
-            if let Some(url) = server_section.get("replay_endpoint") {
-                Box::new(RecordingSendToHttpEndpoint::new(url.to_string()))
+    // Name policy
+    let names_defaults = NamePolicyConfiguration::default();
+    let names = NamePolicyConfiguration {
+        blocked_words: reader.get_list("Names", "blocked_words", "words"),
+        protected_names: reader.get_list("Names", "protected_names", "words"),
+        protect_admin_names: reader.get_bool(
+            "Names",
+            "protect_admin_names",
+            names_defaults.protect_admin_names,
+        ),
+    };
+
+    // Announcements
+    let mut announcements = AnnouncementConfiguration::default();
+    for value in reader.get_all("Announcements", "every") {
+        let announcement = value.split_once(' ').and_then(|(interval, message)| {
+            // An interval of 0 would send the message every tick
+            let minutes = interval.parse::<u64>().ok().filter(|x| *x > 0)?;
+            Some(RepeatedAnnouncement {
+                interval: Duration::from_secs(minutes.checked_mul(60)?),
+                message: message.trim().to_owned(),
+            })
+        });
+        match announcement {
+            Some(announcement) => announcements.repeated.push(announcement),
+            None => reader.error(
+                "Announcements",
+                "every",
+                format!(
+                    "expected at least 1 minute and a message, found \"{}\"",
+                    value
+                ),
+            ),
+        }
+    }
+    announcements.rotation = reader
+        .get_all("Announcements", "rotate")
+        .into_iter()
+        .map(String::from)
+        .collect();
+    announcements.rotation_interval = reader.get_minutes(
+        "Announcements",
+        "rotation_interval",
+        Duration::from_secs(5 * 60),
+    );
+    if announcements.rotation_interval.is_zero() {
+        reader.error(
+            "Announcements",
+            "rotation_interval",
+            "expected at least 1 minute, found 0",
+        );
+    }
+    for value in reader.get_all("Announcements", "at") {
+        // The time is either "HH:MM" or "Weekday HH:MM", followed by the message
+        let announcement = value.split_once(' ').and_then(|(first, rest)| {
+            if let Ok(at) = first.parse::<ScheduledTime>() {
+                Some((at, rest))
             } else {
-                let dir = if let Some(path) = server_section.get("replay_directory") {
-                    PathBuf::from(path)
-                } else {
-                    PathBuf::from("replays")
-                };
-                Box::new(RecordingSaveToFile::new(dir))
+                let (time, message) = rest.split_once(' ')?;
+                let at = format!("{} {}", first, time)
+                    .parse::<ScheduledTime>()
+                    .ok()?;
+                Some((at, message))
+            }
+        });
+        match announcement {
+            Some((at, message)) => announcements.daily.push(DailyAnnouncement {
+                at,
+                message: message.trim().to_owned(),
+            }),
+            None => reader.error(
+                "Announcements",
+                "at",
+                format!("expected a time and a message, found \"{}\"", value),
+            ),
+        }
+    }
+    announcements.period_start = reader
+        .get_all("Announcements", "period_start")
+        .into_iter()
+        .map(String::from)
+        .collect();
+    announcements.next_match = reader.get_list(
+        "Announcements",
+        "next_match",
+        "times like 20:00 or Sunday 20:00",
+    );
+
+    // Administrator roles and accounts
+    let mut roles = AdminRole::default_roles();
+    if let Some(roles_section) = conf.section(Some("Roles")) {
+        for (name, permissions) in roles_section.iter() {
+            let permissions = match permissions.parse::<AdminPermissions>() {
+                Ok(permissions) => permissions,
+                Err(e) => {
+                    reader.error("Roles", name, e);
+                    continue;
+                }
+            };
+            roles.retain(|x| x.name != name);
+            roles.push(AdminRole {
+                name: name.to_owned(),
+                permissions,
+            });
+        }
+    }
+    let mut admins = vec![];
+    if let Some(admins_section) = conf.section(Some("Admins")) {
+        for (name, value) in admins_section.iter() {
+            let Some((role, password)) = value.split_once(',') else {
+                reader.error("Admins", name, "expected role,password");
+                continue;
+            };
+            let Some(role) = roles.iter().find(|x| x.name == role.trim()) else {
+                reader.error("Admins", name, format!("unknown role {}", role));
+                continue;
             };
+            admins.push(AdminAccount {
+                name: name.to_owned(),
+                password: password.trim().to_owned(),
+                role: role.clone(),
+            });
+        }
+    }
 
-        fn get_optional<U, F: FnOnce(&str) -> U>(
-            section: Option<&Properties>,
-            property: &str,
-            default: U,
-            f: F,
-        ) -> U {
-            section.and_then(|x| x.get(property)).map_or(default, f)
+    let mut config = ServerConfiguration {
+        welcome: welcome_str,
+        password: server_password,
+        admins,
+        player_max: server_player_max,
+        allowlist_file,
+        name_ban_file,
+        names,
+        slots,
+        flood,
+        recording_enabled: replays_enabled,
+        server_name,
+        server_service,
+        restart_method,
+        restart_countdown,
+        physics_preset_directory,
+        vote,
+        announcements,
+        warnings,
+        afk,
+        console: None,
+    };
+
+    // Physics
+    let mut physics_config = PhysicsConfiguration {
+        limit_jump_speed,
+        ..Default::default()
+    };
+    for property in PhysicsConfiguration::property_names() {
+        if let Some(value) = reader.get_str("Physics", property) {
+            if let Err(e) = physics_config.set(property, value) {
+                reader.error("Physics", property, e);
+            }
         }
+    }
 
-        let server_service = server_section.get("service").map(|x| x.to_owned());
+    // Game rules
+    let periods = reader.get("Game", "periods", 3, "a number");
 
-        let ban_file = server_section.get("ban_file").map(|x| x.to_owned());
+    let rules_time_period = reader.get("Game", "time_period", 300, "a number of seconds");
+    let rules_time_warmup = reader.get("Game", "time_warmup", 300, "a number of seconds");
+    let rule_time_break = reader.get("Game", "time_break", 10, "a number of seconds");
+    let rule_time_intermission = reader.get("Game", "time_intermission", 20, "a number of seconds");
+    let warmup_pucks = reader.get("Game", "warmup_pucks", 1, "a number");
+    reader.check_range("Game", "warmup_pucks", &warmup_pucks, 1, MAX_PUCKS);
 
-        // Game
-        let game_section = conf.section(Some("Game"));
+    let mercy = reader.get("Game", "mercy", 0, "a number");
+    let first_to = reader.get("Game", "first", 0, "a number");
 
-        let limit_jump_speed = get_optional(game_section, "limit_jump_speed", false, is_true);
+    let icing = reader.get_enum(
+        "Game",
+        "icing",
+        "off",
+        &[
+            ("off", IcingConfiguration::Off),
+            ("on", IcingConfiguration::Touch),
+            ("touch", IcingConfiguration::Touch),
+            ("notouch", IcingConfiguration::NoTouch),
+        ],
+    );
 
-        let config = ServerConfiguration {
-            welcome: welcome_str,
-            password: server_password,
-            player_max: server_player_max,
-            recording_enabled: replays_enabled,
-            server_name,
-            server_service,
-        };
+    let offside = reader.get_enum(
+        "Game",
+        "offside",
+        "off",
+        &[
+            ("off", OffsideConfiguration::Off),
+            ("on", OffsideConfiguration::Delayed),
+            ("delayed", OffsideConfiguration::Delayed),
+            ("immediate", OffsideConfiguration::Immediate),
+            ("imm", OffsideConfiguration::Immediate),
+        ],
+    );
 
-        // Physics
-        let physics_section = conf.section(Some("Physics"));
-        let gravity = get_optional(physics_section, "gravity", 0.000680555, |x| {
-            x.parse::<f32>().unwrap() / 10000.0
-        });
-        let player_acceleration =
-            get_optional(physics_section, "player_acceleration", 0.000208333, |x| {
-                x.parse::<f32>().unwrap() / 10000.0
-            });
-        let player_deceleration =
-            get_optional(physics_section, "player_deceleration", 0.000555555, |x| {
-                x.parse::<f32>().unwrap() / 10000.0
-            });
-        let max_player_speed = get_optional(physics_section, "max_player_speed", 0.05, |x| {
-            x.parse::<f32>().unwrap() / 100.0
-        });
-        let max_player_shift_speed =
-            get_optional(physics_section, "max_player_shift_speed", 0.0333333, |x| {
-                x.parse::<f32>().unwrap() / 100.0
-            });
+    let offside_line = reader.get_enum(
+        "Game",
+        "offsideline",
+        "blue",
+        &[
+            ("blue", OffsideLineConfiguration::OffensiveBlue),
+            ("center", OffsideLineConfiguration::Center),
+        ],
+    );
 
-        let puck_rink_friction = get_optional(physics_section, "puck_rink_friction", 0.05, |x| {
-            x.parse::<f32>().unwrap()
-        });
-        let player_turning = get_optional(physics_section, "player_turning", 0.00041666666, |x| {
-            x.parse::<f32>().unwrap() / 10000.0
-        });
-        let player_shift_turning = get_optional(
-            physics_section,
-            "player_shift_turning",
-            0.00038888888,
-            |x| x.parse::<f32>().unwrap() / 10000.0,
-        );
+    let twoline_pass = reader.get_enum(
+        "Game",
+        "twolinepass",
+        "off",
+        &[
+            ("off", TwoLinePassConfiguration::Off),
+            ("on", TwoLinePassConfiguration::On),
+            ("forward", TwoLinePassConfiguration::Forward),
+            ("double", TwoLinePassConfiguration::Double),
+            ("both", TwoLinePassConfiguration::Double),
+            ("blue", TwoLinePassConfiguration::ThreeLine),
+            ("three", TwoLinePassConfiguration::ThreeLine),
+            ("threeline", TwoLinePassConfiguration::ThreeLine),
+        ],
+    );
 
-        let player_shift_acceleration = get_optional(
-            physics_section,
-            "player_shift_acceleration",
-            0.00027777,
-            |x| x.parse::<f32>().unwrap() / 10000.0,
+    let spawn_point_offset = reader.get("Game", "spawn_offset", 2.75f32, "a number");
+    let spawn_player_altitude = reader.get("Game", "spawn_player_altitude", 1.5f32, "a number");
+    let spawn_puck_altitude = reader.get("Game", "spawn_puck_altitude", 1.5f32, "a number");
+    let spawn_keep_stick_position = reader.get_bool("Game", "spawn_player_keep_stick", false);
+
+    let use_mph = reader.get_bool("Game", "use_mph", false);
+
+    let goal_replay = reader.get_bool("Game", "goal_replay", false);
+
+    let match_config = MatchConfiguration {
+        time_period: rules_time_period,
+        time_warmup: rules_time_warmup,
+        time_break: rule_time_break,
+        time_intermission: rule_time_intermission,
+        mercy,
+        first_to,
+        icing,
+        offside,
+        offside_line,
+        twoline_pass,
+        warmup_pucks,
+        use_mph,
+        goal_replay,
+        periods,
+        spawn_point_offset,
+        spawn_player_altitude,
+        spawn_puck_altitude,
+        spawn_keep_stick_position,
+    };
+
+    let spawn_point = reader.get_enum(
+        "Game",
+        "spawn",
+        "center",
+        &[("center", SpawnPoint::Center), ("bench", SpawnPoint::Bench)],
+    );
+
+    // The number of attempts is used by both the russian and the shootout mode
+    let attempts: Option<u32> = reader.get_optional("Game", "attempts", "a number");
+    let russian_attempts = attempts.unwrap_or(10);
+    let shootout_attempts = attempts.unwrap_or(5);
+
+    reader.warn_unknown_keys(&["Roles", "Admins"]);
+    for warning in &reader.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    for error in &reader.errors {
+        eprintln!("Error: {}", error);
+    }
+    if !reader.errors.is_empty() {
+        eprintln!(
+            "Configuration file {} has {} errors",
+            config_path,
+            reader.errors.len()
         );
+        std::process::exit(1);
+    }
+    if check_config {
+        println!("Configuration file {} is valid", config_path);
+        return Ok(());
+    }
+    let config_warnings = reader.warnings;
 
-        let physics_config = PhysicsConfiguration {
-            gravity,
-            limit_jump_speed,
-            player_acceleration,
-            player_deceleration,
-            player_shift_acceleration,
-            max_player_speed,
-            max_player_shift_speed,
-            puck_rink_friction,
-            player_turning,
-            player_shift_turning,
-        };
+    let terminal_console = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
 
-        let file_appender = tracing_appender::rolling::daily("log", log_name);
-        let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-        tracing_subscriber::fmt()
-            .with_line_number(false)
-            .with_file(false)
-            .with_target(false)
-            .with_writer(non_blocking)
-            .init();
-
-        let ban: Box<dyn BanCheck> = if let Some(ban_file) = ban_file.as_deref() {
-            Box::new(FileBanCheck::new(ban_file.to_string().into()).await?)
-        } else {
-            Box::new(InMemoryBanCheck::new())
-        };
+    let console = if terminal_console || rcon_port.is_some() || rcon_socket.is_some() {
+        let (console, console_receiver) = console_channel();
+        config.console = Some(console_receiver);
+        Some(console)
+    } else {
+        None
+    };
 
-        match mode {
-            HQMServerMode::Match => {
-                let periods =
-                    get_optional(game_section, "periods", 3, |x| x.parse::<u32>().unwrap());
+    let file_appender = tracing_appender::rolling::daily("log", log_name);
+    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    let audit_appender = tracing_appender::rolling::never("log", audit_log_name);
+    let (audit_non_blocking, _audit_guard) = tracing_appender::non_blocking(audit_appender);
+    // Administrator actions are written to their own file as JSON lines
+    let is_audit = |metadata: &tracing::Metadata| metadata.target() == "audit";
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_line_number(false)
+                .with_file(false)
+                .with_target(false)
+                .with_writer(non_blocking)
+                .with_filter(filter_fn(move |x| !is_audit(x))),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .with_level(false)
+                .with_target(false)
+                .with_writer(audit_non_blocking)
+                .with_filter(filter_fn(is_audit)),
+        )
+        .with(console.clone().map(|console| {
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_target(false)
+                .with_writer(console)
+                .with_filter(filter_fn(move |x| !is_audit(x)))
+        }))
+        .init();
 
-                let rules_time_period = get_optional(game_section, "time_period", 300, |x| {
-                    x.parse::<u32>().unwrap()
-                });
-                let rules_time_warmup = get_optional(game_section, "time_warmup", 300, |x| {
-                    x.parse::<u32>().unwrap()
-                });
-                let rule_time_break = get_optional(game_section, "time_break", 10, |x| {
-                    x.parse::<u32>().unwrap()
-                });
-                let rule_time_intermission =
-                    get_optional(game_section, "time_intermission", 20, |x| {
-                        x.parse::<u32>().unwrap()
-                    });
-                let warmup_pucks = get_optional(game_section, "warmup_pucks", 1, |x| {
-                    x.parse::<usize>().unwrap()
-                });
+    for warning in config_warnings {
+        tracing::warn!("Configuration: {}", warning);
+    }
 
-                let mercy = get_optional(game_section, "mercy", 0, |x| x.parse::<u32>().unwrap());
-                let first_to =
-                    get_optional(game_section, "first", 0, |x| x.parse::<u32>().unwrap());
-
-                let icing = get_optional(
-                    game_section,
-                    "icing",
-                    IcingConfiguration::Off,
-                    |x| match x {
-                        "on" | "touch" => IcingConfiguration::Touch,
-                        "notouch" => IcingConfiguration::NoTouch,
-                        _ => IcingConfiguration::Off,
-                    },
-                );
-
-                let offside = get_optional(
-                    game_section,
-                    "offside",
-                    OffsideConfiguration::Off,
-                    |x| match x {
-                        "on" | "delayed" => OffsideConfiguration::Delayed,
-                        "immediate" | "imm" => OffsideConfiguration::Immediate,
-                        _ => OffsideConfiguration::Off,
-                    },
-                );
-
-                let offside_line = get_optional(
-                    game_section,
-                    "offsideline",
-                    OffsideLineConfiguration::OffensiveBlue,
-                    |x| match x {
-                        "blue" => OffsideLineConfiguration::OffensiveBlue,
-                        "center" => OffsideLineConfiguration::Center,
-                        _ => OffsideLineConfiguration::OffensiveBlue,
-                    },
-                );
-
-                let twoline_pass = get_optional(
-                    game_section,
-                    "twolinepass",
-                    TwoLinePassConfiguration::Off,
-                    |x| match x {
-                        "on" => TwoLinePassConfiguration::On,
-                        "forward" => TwoLinePassConfiguration::Forward,
-                        "double" | "both" => TwoLinePassConfiguration::Double,
-                        "blue" | "three" | "threeline" => TwoLinePassConfiguration::ThreeLine,
-                        _ => TwoLinePassConfiguration::Off,
-                    },
-                );
-
-                let spawn_point =
-                    get_optional(game_section, "spawn", SpawnPoint::Center, |x| match x {
-                        "bench" => SpawnPoint::Bench,
-                        _ => SpawnPoint::Center,
-                    });
-
-                let spawn_point_offset = get_optional(game_section, "spawn_offset", 2.75f32, |x| {
-                    x.parse::<f32>().unwrap()
+    if let Some(console) = console.as_ref() {
+        if terminal_console {
+            let console = console.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = migo_hqm_server::console::run_terminal_console(console) {
+                    tracing::error!("Terminal console failed: {}", e);
+                }
+            });
+        }
+        if let Some(rcon_port) = rcon_port {
+            if let Some(rcon_password) = rcon_password {
+                let addr = SocketAddr::new(rcon_address, rcon_port);
+                let console = console.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        migo_hqm_server::console::run_tcp_console(addr, rcon_password, console)
+                            .await
+                    {
+                        tracing::error!("Remote console failed: {}", e);
+                    }
                 });
-
-                let spawn_player_altitude =
-                    get_optional(game_section, "spawn_player_altitude", 1.5f32, |x| {
-                        x.parse::<f32>().unwrap()
-                    });
-
-                let spawn_puck_altitude =
-                    get_optional(game_section, "spawn_puck_altitude", 1.5f32, |x| {
-                        x.parse::<f32>().unwrap()
-                    });
-
-                let spawn_keep_stick_position =
-                    get_optional(game_section, "spawn_player_keep_stick", false, is_true);
-
-                let use_mph = get_optional(game_section, "use_mph", false, is_true);
-
-                let goal_replay = get_optional(game_section, "goal_replay", false, is_true);
-
-                let match_config = MatchConfiguration {
-                    time_period: rules_time_period,
-                    time_warmup: rules_time_warmup,
-                    time_break: rule_time_break,
-                    time_intermission: rule_time_intermission,
-                    mercy,
-                    first_to,
-                    icing,
-                    offside,
-                    offside_line,
-                    twoline_pass,
-                    warmup_pucks,
-                    use_mph,
-                    goal_replay,
-                    periods,
-                    spawn_point_offset,
-                    spawn_player_altitude,
-                    spawn_puck_altitude,
-                    spawn_keep_stick_position,
-                };
-
-                migo_hqm_server::run_server(
-                    server_port,
-                    public_address,
-                    config,
-                    physics_config,
-                    ban,
-                    replay_saving,
-                    StandardMatchGameMode::new(match_config, server_team_max, spawn_point),
-                )
-                .await?
+            } else {
+                tracing::warn!("rcon_port is set without rcon_password, remote console disabled");
             }
-            HQMServerMode::PermanentWarmup => {
-                let warmup_pucks = get_optional(game_section, "warmup_pucks", 1, |x| {
-                    x.parse::<usize>().unwrap()
+        }
+        if let Some(rcon_socket) = rcon_socket {
+            #[cfg(unix)]
+            {
+                let console = console.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        migo_hqm_server::console::run_unix_console(rcon_socket, console).await
+                    {
+                        tracing::error!("Remote console failed: {}", e);
+                    }
                 });
-
-                let spawn_point =
-                    get_optional(game_section, "spawn", SpawnPoint::Center, |x| match x {
-                        "bench" => SpawnPoint::Bench,
-                        _ => SpawnPoint::Center,
-                    });
-
-                migo_hqm_server::run_server(
-                    server_port,
-                    public_address,
-                    config,
-                    physics_config,
-                    ban,
-                    replay_saving,
-                    PermanentWarmup::new(warmup_pucks, spawn_point),
-                )
-                .await?
-            }
-            HQMServerMode::Russian => {
-                let attempts =
-                    get_optional(game_section, "attempts", 10, |x| x.parse::<u32>().unwrap());
-
-                migo_hqm_server::run_server(
-                    server_port,
-                    public_address,
-                    config,
-                    physics_config,
-                    ban,
-                    replay_saving,
-                    RussianGameMode::new(attempts, server_team_max),
-                )
-                .await?
             }
-            HQMServerMode::Shootout => {
-                let attempts =
-                    get_optional(game_section, "attempts", 5, |x| x.parse::<u32>().unwrap());
-
-                migo_hqm_server::run_server(
-                    server_port,
-                    public_address,
-                    config,
-                    physics_config,
-                    ban,
-                    replay_saving,
-                    ShootoutGameMode::new(attempts),
-                )
-                .await?;
+            #[cfg(not(unix))]
+            {
+                let _ = rcon_socket;
+                tracing::warn!("rcon_socket is only supported on Unix systems");
             }
-        };
+        }
+    }
+
+    let file_ban: Option<Box<dyn BanCheck>> = if let Some(ban_file) = ban_file.as_deref() {
+        Some(Box::new(
+            FileBanCheck::new(ban_file.to_string().into()).await?,
+        ))
+    } else {
+        None
+    };
+    let endpoint_ban: Option<Box<dyn BanCheck>> = if let Some(ban_endpoint) = ban_endpoint {
+        let req =
+            HttpBanCheckRequests::new(ban_endpoint, ban_endpoint_token, ban_endpoint_timeout)?;
+        Some(Box::new(
+            ExternalBanCheck::new(req).with_fallback(ban_endpoint_fallback),
+        ))
     } else {
-        println!("Could not open configuration file {}!", config_path);
+        None
     };
+    let (primary_ban, other_ban) = match ban_primary {
+        "endpoint" => (endpoint_ban, file_ban),
+        _ => (file_ban, endpoint_ban),
+    };
+    // The primary source was checked with the configuration, it is only missing if no source is configured
+    let primary_ban = primary_ban.unwrap_or_else(|| Box::new(InMemoryBanCheck::new()));
+    let mut other_bans: Vec<Box<dyn BanCheck>> = other_ban.into_iter().collect();
+    for path in ban_lists {
+        other_bans.push(Box::new(FileBanCheck::new_read_only(path).await?));
+    }
+    let ban: Box<dyn BanCheck> = if other_bans.is_empty() {
+        primary_ban
+    } else {
+        Box::new(LayeredBanCheck::new(primary_ban, other_bans))
+    };
+
+    // Game modes that can be selected with /mode, the argument is the team size, number of pucks or number of attempts
+    let modes: Vec<(String, GameModeFactory)> = vec![
+        (
+            "match".to_owned(),
+            Box::new(move |arg| {
+                let team_max = parse_mode_arg(arg, server_team_max, 1..=MAX_TEAM_SIZE)?;
+                Some(Box::new(StandardMatchGameMode::new(
+                    match_config.clone(),
+                    team_max,
+                    spawn_point,
+                )))
+            }),
+        ),
+        (
+            "warmup".to_owned(),
+            Box::new(move |arg| {
+                let pucks = parse_mode_arg(arg, warmup_pucks, 1..=MAX_PUCKS)?;
+                Some(Box::new(PermanentWarmup::new(pucks, spawn_point)))
+            }),
+        ),
+        (
+            "russian".to_owned(),
+            Box::new(move |arg| {
+                let attempts = parse_mode_arg(arg, russian_attempts, 1..)?;
+                Some(Box::new(RussianGameMode::new(attempts, server_team_max)))
+            }),
+        ),
+        (
+            "shootout".to_owned(),
+            Box::new(move |arg| {
+                let attempts = parse_mode_arg(arg, shootout_attempts, 1..)?;
+                Some(Box::new(ShootoutGameMode::new(attempts)))
+            }),
+        ),
+    ];
+
+    migo_hqm_server::run_server(
+        server_port,
+        public_address,
+        config,
+        physics_config,
+        ban,
+        replay_saving,
+        SwitchableGameMode::new(modes, mode).unwrap(),
+    )
+    .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_mode_arg, ConfigReader};
+    use ini::Ini;
+    use migo_hqm_server::gamemode::warmup::MAX_PUCKS;
+    use std::time::Duration;
+
+    #[test]
+    fn mode_arg_range() {
+        assert_eq!(parse_mode_arg("", 5, 1..=15), Some(5));
+        assert_eq!(parse_mode_arg("3", 5, 1..=15), Some(3));
+        assert_eq!(parse_mode_arg("0", 5, 1..=15), None);
+        assert_eq!(parse_mode_arg("500", 5, 1..=15), None);
+        assert_eq!(parse_mode_arg("40", 1, 1..=MAX_PUCKS), None);
+        assert_eq!(parse_mode_arg("0", 10, 1..), None);
+        assert_eq!(parse_mode_arg("x", 10, 1..), None);
+    }
+
+    #[test]
+    fn config_reader() {
+        let conf = Ini::load_from_str(
+            "[Server]\nport=27x99\npublic=on\ntypo=1\n[Game]\nicing=tuch\n[Unknown]\nkey=1\n",
+        )
+        .unwrap();
+        let mut reader = ConfigReader::new(&conf);
+        let _: u16 = reader.require("Server", "port", "a port number");
+        let _: usize = reader.require("Server", "team_max", "a number");
+        assert!(reader.get_bool("Server", "public", false));
+        assert_eq!(
+            reader.get_enum("Game", "icing", "off", &[("off", 0), ("touch", 1)]),
+            0
+        );
+        reader.warn_unknown_keys(&[]);
+        assert_eq!(
+            reader.errors,
+            [
+                "[Server] port: expected a port number, found \"27x99\"",
+                "[Server] team_max: missing, expected a number",
+            ]
+        );
+        assert_eq!(reader.warnings.len(), 3);
+    }
+
+    #[test]
+    fn config_reader_ranges() {
+        let conf = Ini::load_from_str(
+            "[Vote]\nkick_threshold=1.5\nmute_threshold=0.5\n[Announcements]\ninterval=999999999999999999\n",
+        )
+        .unwrap();
+        let mut reader = ConfigReader::new(&conf);
+        for key in ["kick_threshold", "mute_threshold"] {
+            let value: f32 = reader.get("Vote", key, 0.5, "a number");
+            reader.check_range("Vote", key, &value, 0.0, 1.0);
+        }
+        let interval = reader.get_minutes("Announcements", "interval", Duration::from_secs(60));
+        assert_eq!(interval, Duration::from_secs(60));
+        assert_eq!(
+            reader.errors,
+            [
+                "[Vote] kick_threshold: expected a number from 0 to 1, found 1.5",
+                "[Announcements] interval: 999999999999999999 minutes is too long",
+            ]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use regex::{Regex, RegexBuilder};
+use tracing::info;
+
+use crate::admin::AdminPermission;
+use crate::ban::{write_ban_file, BanInfo};
+use crate::game::PlayerId;
+use crate::gamemode::{ExitReason, GameMode};
+use crate::server::{HQMServer, PlayerListExt, ServerPlayerData};
+
+enum Matching {
+    Equals(String),
+    StartsWith(String),
+    EndsWith(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+/// Player name pattern used by /kickall, /banall and name bans.
+///
+/// Patterns are case-insensitive. % at the end or start matches any text, so migo% matches names
+/// starting with migo, %migo names ending with it and %migo% names containing it.
+/// A pattern between slashes, like /^m[i1]go$/, is a regular expression.
+pub struct NamePattern {
+    text: String,
+    matching: Matching,
+}
+
+impl NamePattern {
+    /// Parses a pattern. Returns [None] for invalid regular expressions and patterns that match every name.
+    pub fn parse(s: &str) -> Option<Self> {
+        let matching = if let Some(re) = s
+            .strip_prefix('/')
+            .and_then(|x| x.strip_suffix('/'))
+            .filter(|x| !x.is_empty())
+        {
+            let re = RegexBuilder::new(re).case_insensitive(true).build().ok()?;
+            // Reject expressions like /.*/ or /./ that would match every name
+            if re.is_match("")
+                || ["a", "z", "0", "9", ".", "-", " "]
+                    .iter()
+                    .all(|x| re.is_match(x))
+            {
+                return None;
+            }
+            Matching::Regex(re)
+        } else {
+            let s = s.to_lowercase();
+            match (s.strip_prefix('%'), s.strip_suffix('%')) {
+                (Some(x), Some(_)) => Matching::Contains(x.strip_suffix('%')?.to_owned()),
+                (Some(x), None) => Matching::EndsWith(x.to_owned()),
+                (None, Some(x)) => Matching::StartsWith(x.to_owned()),
+                (None, None) => Matching::Equals(s),
+            }
+        };
+        if let Matching::Equals(x)
+        | Matching::StartsWith(x)
+        | Matching::EndsWith(x)
+        | Matching::Contains(x) = &matching
+        {
+            if x.is_empty() {
+                return None;
+            }
+        }
+        Some(Self {
+            text: s.to_owned(),
+            matching,
+        })
+    }
+
+    pub fn is_matching(&self, player_name: &str) -> bool {
+        let player_name = player_name.to_lowercase();
+        match &self.matching {
+            Matching::Equals(s) => player_name == *s,
+            Matching::StartsWith(s) => player_name.starts_with(s),
+            Matching::EndsWith(s) => player_name.ends_with(s),
+            Matching::Contains(s) => player_name.contains(s),
+            Matching::Regex(re) => re.is_match(&player_name),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Rules for the names players can join with.
+///
+/// Names are compared after removing everything but letters and digits and replacing digits
+/// that look like letters, so "4dm1n" and "A.D.M.I.N" both count as "admin".
+#[derive(Debug, Clone, Default)]
+pub struct NamePolicyConfiguration {
+    /// Names containing one of these words are rejected.
+    pub blocked_words: Vec<String>,
+    /// Names containing one of these, like "admin" or "server", are rejected.
+    pub protected_names: Vec<String>,
+    /// Rejects names that look like the name of an administrator account. The exact account name
+    /// is only allowed from addresses in reserved_ips, because players are not logged in when they join.
+    pub protect_admin_names: bool,
+}
+
+fn normalize_name(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+impl NamePolicyConfiguration {
+    /// Returns true if a player can join with this name. `exact_admin_names` is true if the player
+    /// may use the exact name of an administrator account.
+    pub(crate) fn is_allowed(
+        &self,
+        player_name: &str,
+        admin_names: &[&str],
+        exact_admin_names: bool,
+    ) -> bool {
+        let name = normalize_name(player_name);
+        let contains = |words: &[String]| {
+            words
+                .iter()
+                .map(|x| normalize_name(x))
+                .any(|x| !x.is_empty() && name.contains(&x))
+        };
+        let impersonates_admin = self.protect_admin_names
+            && admin_names
+                .iter()
+                .any(|x| !(exact_admin_names && *x == player_name) && normalize_name(x) == name);
+        !contains(&self.blocked_words) && !contains(&self.protected_names) && !impersonates_admin
+    }
+}
+
+/// Player name pattern bans, stored in a text file if one is configured.
+///
+/// Each line is a pattern, optionally followed by the same tab-separated fields as the ban file.
+pub(crate) struct NameBanList {
+    file: Option<PathBuf>,
+    bans: Vec<(NamePattern, BanInfo)>,
+}
+
+impl NameBanList {
+    pub(crate) async fn new(file: Option<PathBuf>) -> Result<Self, tokio::io::Error> {
+        let mut bans = vec![];
+        if let Some(path) = &file {
+            let s = match tokio::fs::read_to_string(path).await {
+                Ok(s) => s,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e),
+            };
+            for line in s.lines() {
+                let (pattern, fields) = line.split_once('\t').unwrap_or((line, ""));
+                if let Some(pattern) = NamePattern::parse(pattern.trim()) {
+                    bans.push((pattern, BanInfo::parse(fields)));
+                }
+            }
+        }
+        Ok(Self { file, bans })
+    }
+
+    /// Returns true if a pattern ban that hasn't expired matches a player name.
+    fn is_banned(&self, player_name: &str) -> bool {
+        let now = Utc::now();
+        self.bans
+            .iter()
+            .any(|(pattern, info)| !info.is_expired(now) && pattern.is_matching(player_name))
+    }
+
+    fn insert(&mut self, pattern: NamePattern, info: BanInfo) {
+        self.bans
+            .retain(|(x, _)| !x.text.eq_ignore_ascii_case(&pattern.text));
+        self.bans.push((pattern, info));
+        self.save();
+    }
+
+    fn remove(&mut self, pattern: &str) -> bool {
+        let len = self.bans.len();
+        self.bans
+            .retain(|(x, _)| !x.text.eq_ignore_ascii_case(pattern));
+        let removed = len != self.bans.len();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    fn save(&mut self) {
+        let Some(path) = self.file.clone() else {
+            return;
+        };
+        let now = Utc::now();
+        self.bans.retain(|(_, info)| !info.is_expired(now));
+        let mut s = String::new();
+        for (pattern, info) in &self.bans {
+            s.push_str(&pattern.text);
+            info.write_fields(&mut s);
+            s.push('\n');
+        }
+        tokio::spawn(async move { write_ban_file(&path, &s).await });
+    }
+}
+
+impl HQMServer {
+    /// Checks a joining player's name against the name bans and the name policy.
+    pub(crate) fn is_name_allowed(&self, player_name: &str, ip: IpAddr) -> bool {
+        if self.name_bans.is_banned(player_name) {
+            return false;
+        }
+        let admin_names: Vec<&str> = self
+            .config
+            .admins
+            .iter()
+            .map(|account| account.name.as_str())
+            .collect();
+        let reserved_ip = self.config.slots.reserved_ips.contains(&ip);
+        self.config
+            .names
+            .is_allowed(player_name, &admin_names, reserved_ip)
+    }
+
+    /// Bans a name pattern and kicks the players that match it.
+    pub(crate) fn ban_name<B: GameMode>(
+        &mut self,
+        admin_player_id: PlayerId,
+        arg: &str,
+        behaviour: &mut B,
+    ) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Ban,
+            "banname",
+        ) {
+            let admin_player_name = player.player_name.clone();
+            let (pattern, reason) = arg.split_once(' ').unwrap_or((arg, ""));
+            let Some(pattern) = NamePattern::parse(pattern) else {
+                self.state.players.add_directed_server_chat_message(
+                    "Usage: /banname <name pattern> [reason]",
+                    admin_player_id,
+                );
+                return;
+            };
+            let info = BanInfo::new(None, &admin_player_name, reason.trim(), None);
+            info!(
+                "{} ({}) banned name {}",
+                admin_player_name,
+                admin_player_id,
+                pattern.as_str()
+            );
+            self.audit(admin_player_id, "banname", arg, None, Ok(()));
+            let msg = format!("Name {} banned by {}", pattern.as_str(), admin_player_name);
+            self.state.players.add_server_chat_message(msg);
+
+            let kick_player_list: Vec<_> = self
+                .state
+                .players
+                .players
+                .iter_players()
+                .filter(|(player_id, player)| {
+                    *player_id != admin_player_id
+                        && matches!(player.data, ServerPlayerData::NetworkPlayer { .. })
+                        && pattern.is_matching(&player.player_name)
+                })
+                .map(|(player_id, player)| (player_id, player.player_name.clone()))
+                .collect();
+            self.name_bans.insert(pattern, info);
+            for (player_id, player_name) in kick_player_list {
+                info!(
+                    "{} ({}) kicked {} ({})",
+                    admin_player_name, admin_player_id, player_name, player_id
+                );
+                self.kick_player_by(
+                    player_id,
+                    None,
+                    &admin_player_name,
+                    ExitReason::AdminKicked,
+                    behaviour,
+                );
+            }
+        }
+    }
+
+    pub(crate) fn unban_name(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Ban,
+            "unbanname",
+        ) {
+            let admin_player_name = player.player_name.clone();
+            if !self.name_bans.remove(arg) {
+                let msg = format!("No name ban found for {}", arg);
+                self.state
+                    .players
+                    .add_directed_server_chat_message(msg, admin_player_id);
+                self.audit(admin_player_id, "unbanname", arg, None, Err("not banned"));
+                return;
+            }
+            info!(
+                "{} ({}) removed name ban {}",
+                admin_player_name, admin_player_id, arg
+            );
+            self.audit(admin_player_id, "unbanname", arg, None, Ok(()));
+            let msg = format!("Name {} unbanned by {}", arg, admin_player_name);
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
+    pub(crate) fn list_name_bans(&mut self, admin_player_id: PlayerId) {
+        if self
+            .state
+            .players
+            .players
+            .check_permission_or_deny(admin_player_id, AdminPermission::Ban, "namebans")
+            .is_none()
+        {
+            return;
+        }
+        let now = Utc::now();
+        let msgs: Vec<_> = self
+            .name_bans
+            .bans
+            .iter()
+            .filter(|(_, info)| !info.is_expired(now))
+            .map(|(pattern, info)| match &info.reason {
+                Some(reason) => format!("{}: {}", pattern.as_str(), reason),
+                None => pattern.as_str().to_owned(),
+            })
+            .collect();
+        if msgs.is_empty() {
+            self.state
+                .players
+                .add_directed_server_chat_message("No name bans", admin_player_id);
+        }
+        for msg in msgs {
+            self.state
+                .players
+                .add_directed_server_chat_message(msg, admin_player_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::names::{NamePattern, NamePolicyConfiguration};
+
+    #[test]
+    fn name_patterns() {
+        let matches =
+            |pattern: &str, name: &str| NamePattern::parse(pattern).unwrap().is_matching(name);
+        assert!(matches("migo", "Migo"));
+        assert!(!matches("migo", "MigoMipo"));
+        assert!(matches("migo%", "MigoMipo"));
+        assert!(matches("%mipo", "MigoMipo"));
+        assert!(matches("%gomi%", "MigoMipo"));
+        assert!(!matches("%gomi", "MigoMipo"));
+        assert!(matches("/^m[i1]go$/", "M1GO"));
+        assert!(NamePattern::parse("%%").is_none());
+        assert!(NamePattern::parse("/(/").is_none());
+        assert!(NamePattern::parse("/.*/").is_none());
+        assert!(NamePattern::parse("/./").is_none());
+        assert!(NamePattern::parse("/^/").is_none());
+    }
+
+    #[test]
+    fn name_policy() {
+        let policy = NamePolicyConfiguration {
+            blocked_words: vec!["badword".to_owned()],
+            protected_names: vec!["admin".to_owned()],
+            protect_admin_names: true,
+        };
+        let admin_names = ["Migo"];
+        assert!(policy.is_allowed("Player", &admin_names, false));
+        assert!(!policy.is_allowed("B4D-W0RD", &admin_names, false));
+        assert!(!policy.is_allowed("[4dm1n] Bob", &admin_names, false));
+        assert!(!policy.is_allowed("Migo", &admin_names, false));
+        assert!(policy.is_allowed("Migo", &admin_names, true));
+        assert!(!policy.is_allowed("M1go", &admin_names, true));
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
 
 pub trait RecordingSaveMethod {
     fn save_recording_data(
@@ -12,15 +13,24 @@ pub trait RecordingSaveMethod {
         replay_data: Bytes,
         start_time: DateTime<Utc>,
     );
+
+    /// Returns the saves that are still in progress, so they can be awaited before the server restarts.
+    fn take_pending(&mut self) -> Vec<JoinHandle<()>> {
+        vec![]
+    }
 }
 
 pub struct RecordingSaveToFile {
     directory: PathBuf,
+    pending: Vec<JoinHandle<()>>,
 }
 
 impl RecordingSaveToFile {
     pub fn new(directory: PathBuf) -> Self {
-        Self { directory }
+        Self {
+            directory,
+            pending: vec![],
+        }
     }
 }
 
@@ -36,7 +46,8 @@ impl RecordingSaveMethod for RecordingSaveToFile {
         let directory = self.directory.clone();
         let path = self.directory.join(&file_name);
 
-        tokio::spawn(async move {
+        self.pending.retain(|x| !x.is_finished());
+        self.pending.push(tokio::spawn(async move {
             if tokio::fs::create_dir_all(directory).await.is_err() {
                 return;
             };
@@ -50,13 +61,18 @@ impl RecordingSaveMethod for RecordingSaveToFile {
 
             let _x = file_handle.write(&replay_data).await;
             let _x = file_handle.sync_all().await;
-        });
+        }));
+    }
+
+    fn take_pending(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.pending)
     }
 }
 
 pub struct RecordingSendToHttpEndpoint {
     url: String,
     client: reqwest::Client,
+    pending: Vec<JoinHandle<()>>,
 }
 
 impl RecordingSendToHttpEndpoint {
@@ -64,6 +80,7 @@ impl RecordingSendToHttpEndpoint {
         Self {
             url,
             client: reqwest::Client::new(),
+            pending: vec![],
         }
     }
 }
@@ -88,8 +105,13 @@ impl RecordingSaveMethod for RecordingSendToHttpEndpoint {
             );
 
         let request = client.post(&self.url).multipart(form);
-        tokio::spawn(async move {
+        self.pending.retain(|x| !x.is_finished());
+        self.pending.push(tokio::spawn(async move {
             let _x = request.send().await;
-        });
+        }));
+    }
+
+    fn take_pending(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.pending)
     }
 }
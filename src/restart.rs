@@ -0,0 +1,378 @@
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::admin::AdminPermission;
+use crate::game::PlayerId;
+use crate::server::{HQMServer, PlayerListExt};
+use crate::ReplayRecording;
+
+/// How the server restarts itself after /serverrestart.
+#[derive(Debug, Clone)]
+pub enum RestartMethod {
+    /// Restarts the given systemd unit with systemctl.
+    Systemd(String),
+    /// Replaces the running process with a new instance of the same binary and arguments.
+    Exec,
+    /// Exits with the given code, for supervisors that restart the server themselves.
+    Exit(i32),
+}
+
+/// Seconds before the restart at which players are warned.
+const RESTART_WARNINGS: [u64; 10] = [300, 120, 60, 30, 10, 5, 4, 3, 2, 1];
+
+#[derive(Default)]
+pub(crate) struct RestartState {
+    at: Option<Instant>,
+    after_game: bool,
+    /// Whether the game was over in the last tick, to notice when a game ends.
+    game_over: bool,
+    last_warning: Option<u64>,
+}
+
+/// What the server should do about a pending restart in this tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RestartAction {
+    Warn(u64),
+    Restart,
+}
+
+impl RestartState {
+    fn schedule(&mut self, at: Instant) {
+        self.at = Some(at);
+        self.last_warning = None;
+    }
+
+    /// Cancels a scheduled restart. Returns false if no restart was scheduled.
+    fn cancel(&mut self) -> bool {
+        let scheduled = self.at.is_some() || self.after_game;
+        self.at = None;
+        self.after_game = false;
+        self.last_warning = None;
+        scheduled
+    }
+
+    /// A restart requested with /serverrestart endgame starts its countdown when the game is over,
+    /// or restarts right away when no players are left, like in game modes where games never end.
+    fn update(
+        &mut self,
+        now: Instant,
+        game_over: bool,
+        player_count: usize,
+        countdown: Duration,
+    ) -> Option<RestartAction> {
+        if self.after_game && player_count == 0 {
+            return Some(RestartAction::Restart);
+        }
+        if game_over && !self.game_over && self.after_game {
+            self.after_game = false;
+            self.schedule(now + countdown);
+        }
+        self.game_over = game_over;
+
+        let at = self.at?;
+        if now >= at || player_count == 0 {
+            return Some(RestartAction::Restart);
+        }
+        let seconds = (at - now).as_millis().div_ceil(1000) as u64;
+        if RESTART_WARNINGS.contains(&seconds) && self.last_warning != Some(seconds) {
+            self.last_warning = Some(seconds);
+            return Some(RestartAction::Warn(seconds));
+        }
+        None
+    }
+}
+
+impl HQMServer {
+    pub(crate) fn restart_server(&mut self, admin_player_id: PlayerId, arg: &str) {
+        if let Some(player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Server,
+            "serverrestart",
+        ) {
+            let player_name = player.player_name.clone();
+            if self.config.restart_method.is_none() {
+                self.state.players.add_directed_server_chat_message(
+                    "Server restart is not configured",
+                    admin_player_id,
+                );
+                self.audit(
+                    admin_player_id,
+                    "serverrestart",
+                    arg,
+                    None,
+                    Err("not configured"),
+                );
+                return;
+            }
+            let msg = match arg {
+                "cancel" => {
+                    if !self.restart.cancel() {
+                        self.state.players.add_directed_server_chat_message(
+                            "No restart is scheduled",
+                            admin_player_id,
+                        );
+                        return;
+                    }
+                    info!(
+                        "{} ({}) cancelled server restart",
+                        player_name, admin_player_id
+                    );
+                    format!("Server restart cancelled by {}", player_name)
+                }
+                "endgame" => {
+                    self.restart.after_game = true;
+                    info!(
+                        "{} ({}) scheduled server restart after the current game",
+                        player_name, admin_player_id
+                    );
+                    format!(
+                        "{} scheduled server restart after the current game",
+                        player_name
+                    )
+                }
+                _ => {
+                    let countdown = if arg.is_empty() {
+                        self.config.restart_countdown
+                    } else if let Ok(seconds) = arg.parse::<u64>() {
+                        Duration::from_secs(seconds)
+                    } else {
+                        return;
+                    };
+                    self.restart.schedule(Instant::now() + countdown);
+                    info!(
+                        "{} ({}) started server restart in {} seconds",
+                        player_name,
+                        admin_player_id,
+                        countdown.as_secs()
+                    );
+                    format!(
+                        "{} started server restart in {} seconds",
+                        player_name,
+                        countdown.as_secs()
+                    )
+                }
+            };
+            self.state.players.add_server_chat_message(msg);
+            self.audit(admin_player_id, "serverrestart", arg, None, Ok(()));
+        }
+    }
+
+    /// Warns players about a pending restart. Returns true when it is time to restart.
+    pub(crate) fn update_restart(&mut self) -> bool {
+        let player_count = self.real_player_count();
+        match self.restart.update(
+            Instant::now(),
+            self.state.scoreboard.game_over,
+            player_count,
+            self.config.restart_countdown,
+        ) {
+            Some(RestartAction::Restart) => true,
+            Some(RestartAction::Warn(seconds)) => {
+                let msg = format!("Server restarting in {} seconds", seconds);
+                self.state.players.add_server_chat_message(msg);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Saves the current recording and restarts the server with the configured method.
+    ///
+    /// If the restart fails, the server keeps running and the current game is still recorded.
+    pub(crate) async fn restart(&mut self) {
+        let Some(restart_method) = self.config.restart_method.clone() else {
+            return;
+        };
+        info!("Restarting server");
+        self.restart.cancel();
+        let res = match check_restart_method(&restart_method) {
+            Ok(()) => {
+                self.save_all_recordings().await;
+                // Give the log writer time to write the last lines before the process is replaced
+                tokio::time::sleep(Duration::from_millis(100)).await;
+
+                match restart_method {
+                    RestartMethod::Systemd(service) => restart_systemd(&service),
+                    RestartMethod::Exec => Err(exec_self()),
+                    RestartMethod::Exit(code) => std::process::exit(code),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            warn!("Restart failed: {}", e);
+            self.state
+                .players
+                .add_server_chat_message("Server restart failed");
+        }
+    }
+
+    /// Saves the current recording before the server stops.
+    pub(crate) async fn shutdown(&mut self) {
+        info!("Stopping server");
+        self.save_all_recordings().await;
+    }
+
+    /// Saves the recording of the current game and waits for all recordings to be saved.
+    ///
+    /// The current game keeps its recording data, so that it is still complete if the server
+    /// keeps running.
+    async fn save_all_recordings(&mut self) {
+        let recording_data = self.state.recording_data.clone();
+        if self.config.recording_enabled == ReplayRecording::On && !recording_data.is_empty() {
+            self.save_recording(&recording_data);
+        }
+        let pending = self.save_recording.take_pending();
+        if tokio::time::timeout(Duration::from_secs(10), futures::future::join_all(pending))
+            .await
+            .is_err()
+        {
+            warn!("Timed out while saving recordings");
+        }
+    }
+}
+
+/// Checks that the restart method can work before the recordings are saved.
+fn check_restart_method(restart_method: &RestartMethod) -> std::io::Result<()> {
+    match restart_method {
+        RestartMethod::Systemd(service) => {
+            if systemctl::SystemCtl::default().exists(service)? {
+                Ok(())
+            } else {
+                Err(std::io::Error::other(format!(
+                    "unit {} does not exist",
+                    service
+                )))
+            }
+        }
+        RestartMethod::Exec => std::env::current_exe().map(|_| ()),
+        RestartMethod::Exit(_) => Ok(()),
+    }
+}
+
+fn restart_systemd(service: &str) -> std::io::Result<()> {
+    let status = systemctl::SystemCtl::default().restart(service)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("systemctl {}", status)))
+    }
+}
+
+/// Replaces the running process. Only returns if that failed.
+#[cfg(unix)]
+fn exec_self() -> std::io::Error {
+    use std::os::unix::process::CommandExt;
+
+    std::env::current_exe()
+        .map(|exe| {
+            std::process::Command::new(exe)
+                .args(std::env::args_os().skip(1))
+                .exec()
+        })
+        .unwrap_or_else(|e| e)
+}
+
+#[cfg(not(unix))]
+fn exec_self() -> std::io::Error {
+    std::io::Error::other("replacing the process is only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::restart::{RestartAction, RestartState};
+    use std::time::{Duration, Instant};
+
+    const COUNTDOWN: Duration = Duration::from_secs(30);
+
+    /// Runs the restart state once per second, returning the actions taken.
+    fn run(
+        state: &mut RestartState,
+        start: Instant,
+        seconds: std::ops::Range<u64>,
+        game_over: bool,
+        player_count: usize,
+    ) -> Vec<(u64, RestartAction)> {
+        seconds
+            .filter_map(|second| {
+                let now = start + Duration::from_secs(second);
+                state
+                    .update(now, game_over, player_count, COUNTDOWN)
+                    .map(|action| (second, action))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restart_countdown() {
+        let start = Instant::now();
+        let mut state = RestartState::default();
+        assert_eq!(run(&mut state, start, 0..100, false, 4), vec![]);
+
+        state.schedule(start + Duration::from_secs(130));
+        let actions = run(&mut state, start, 0..131, false, 4);
+        let warnings: Vec<_> = actions
+            .iter()
+            .filter_map(|(_, action)| match action {
+                RestartAction::Warn(seconds) => Some(*seconds),
+                RestartAction::Restart => None,
+            })
+            .collect();
+        assert_eq!(warnings, vec![120, 60, 30, 10, 5, 4, 3, 2, 1]);
+        assert_eq!(actions.last(), Some(&(130, RestartAction::Restart)));
+
+        // An empty server restarts right away
+        let mut state = RestartState::default();
+        state.schedule(start + Duration::from_secs(130));
+        assert_eq!(
+            run(&mut state, start, 0..1, false, 0),
+            vec![(0, RestartAction::Restart)]
+        );
+    }
+
+    #[test]
+    fn restart_cancel() {
+        let start = Instant::now();
+        let mut state = RestartState::default();
+        assert!(!state.cancel());
+
+        state.schedule(start + Duration::from_secs(20));
+        assert_eq!(run(&mut state, start, 0..11, false, 4).len(), 1);
+        assert!(state.cancel());
+        assert_eq!(run(&mut state, start, 0..100, false, 4), vec![]);
+
+        // Scheduling again warns again
+        state.schedule(start + Duration::from_secs(20));
+        assert_eq!(
+            run(&mut state, start, 10..11, false, 4),
+            vec![(10, RestartAction::Warn(10))]
+        );
+
+        state.cancel();
+        state.after_game = true;
+        assert!(state.cancel());
+        assert_eq!(run(&mut state, start, 0..10, true, 4), vec![]);
+    }
+
+    #[test]
+    fn restart_after_game() {
+        let start = Instant::now();
+        let mut state = RestartState::default();
+        state.after_game = true;
+        assert_eq!(run(&mut state, start, 0..100, false, 4), vec![]);
+
+        // The countdown starts when the game ends
+        let actions = run(&mut state, start, 100..131, true, 4);
+        assert_eq!(actions.first(), Some(&(100, RestartAction::Warn(30))));
+        assert_eq!(actions.last(), Some(&(130, RestartAction::Restart)));
+
+        // Without players, there is no game to wait for
+        let mut state = RestartState::default();
+        state.after_game = true;
+        assert_eq!(
+            run(&mut state, start, 0..1, false, 0),
+            vec![(0, RestartAction::Restart)]
+        );
+    }
+}
@@ -20,18 +20,28 @@ use tokio::net::UdpSocket;
 use tokio::time::MissedTickBehavior;
 use tracing::{info, warn};
 
-use crate::gamemode::{ExitReason, GameMode, InitialGameValues};
-
-use crate::ban::{BanCheck, BanCheckResponse};
+use crate::gamemode::{ExitReason, GameMode, GameVote, InitialGameValues};
+
+use crate::admin::{AdminLogin, AdminPermission};
+use crate::afk::AfkState;
+use crate::allowlist::Allowlist;
+use crate::announcement::AnnouncementState;
+use crate::ban::{parse_ban_duration, BanCheck, BanCheckResponse};
+use crate::console::{ConsoleCommand, ConsoleOutput, ConsoleReceiver};
+use crate::flood::FloodState;
 use crate::game::{
     PhysicsConfiguration, PlayerId, PlayerIndex, PlayerInput, Puck, Rink, RulesState,
     ScoreboardValues, SkaterHand, SkaterObject, Team,
 };
+use crate::names::NameBanList;
 use crate::protocol::{
     write_message, write_objects, HQMClientToServerMessage, HQMMessageCodec, HQMMessageWriter,
     ObjectPacket,
 };
 use crate::record::RecordingSaveMethod;
+use crate::restart::RestartState;
+use crate::vote::{VoteKind, VoteState};
+use crate::warning::WarningState;
 use crate::{ReplayRecording, ServerConfiguration};
 
 pub(crate) const GAME_HEADER: &[u8] = b"Hock";
@@ -102,13 +112,45 @@ pub(crate) trait PlayerListExt {
 
     fn iter_players_mut(&mut self) -> impl Iterator<Item = (PlayerId, &mut HQMServerPlayer)>;
 
-    fn check_admin_or_deny(&mut self, player_id: PlayerId) -> Option<&HQMServerPlayer> {
+    /// Returns the player if they are logged in with the given permission. Otherwise tells the
+    /// player why the command was denied and records the attempt in the audit log.
+    fn check_permission_or_deny(
+        &mut self,
+        player_id: PlayerId,
+        permission: AdminPermission,
+        command: &str,
+    ) -> Option<&HQMServerPlayer> {
         if let Some(player) = self.get_player_mut(player_id) {
-            if player.is_admin {
-                Some(player)
-            } else {
-                player.add_directed_server_chat_message("Please log in before using that command");
-                None
+            match &player.admin {
+                Some(admin) if admin.permissions.contains(permission) => Some(player),
+                Some(_) => {
+                    audit_log(
+                        player_id,
+                        player,
+                        command,
+                        "",
+                        None,
+                        Err("permission denied"),
+                    );
+                    player.add_directed_server_chat_message(
+                        "You do not have permission to use that command",
+                    );
+                    None
+                }
+                None => {
+                    audit_log(
+                        player_id,
+                        player,
+                        command,
+                        "",
+                        None,
+                        Err("permission denied"),
+                    );
+                    player.add_directed_server_chat_message(
+                        "Please log in before using that command",
+                    );
+                    None
+                }
             }
         } else {
             None
@@ -139,6 +181,34 @@ pub(crate) trait PlayerListExt {
     }
 }
 
+/// Writes an audit record.
+///
+/// The audit log is written as JSON lines to its own file, separate from the general log.
+fn audit_log(
+    admin_player_id: PlayerId,
+    admin: &HQMServerPlayer,
+    command: &str,
+    args: &str,
+    target: Option<(PlayerId, &HQMServerPlayer)>,
+    result: Result<(), &str>,
+) {
+    info!(
+        target: "audit",
+        admin = %admin.player_name,
+        admin_id = %admin_player_id,
+        admin_ip = admin.ip().map(tracing::field::display),
+        account = admin.admin.as_ref().and_then(|x| x.account.as_deref()),
+        role = admin.admin.as_ref().map(|x| x.role.as_str()),
+        command,
+        args,
+        target = target.map(|(_, x)| tracing::field::display(&x.player_name)),
+        target_id = target.map(|(id, _)| tracing::field::display(id)),
+        target_ip = target.and_then(|(_, x)| x.ip()).map(tracing::field::display),
+        result = if result.is_ok() { "ok" } else { "failed" },
+        error = result.err(),
+    );
+}
+
 pub(crate) type ServerStatePlayerItem = (u32, Option<HQMServerPlayer>);
 
 impl PlayerListExt for [ServerStatePlayerItem] {
@@ -227,10 +297,24 @@ pub(crate) struct HQMServerPlayersAndMessages {
     recording_messages: Vec<Rc<HQMMessage>>,
 
     puck_slots: usize,
+
+    /// If true, players can only be moved to a team by administrators.
+    pub(crate) teams_locked: bool,
+
+    /// The command a game mode is handling, so that it can be audited once it has been handled.
+    pub(crate) game_mode_command: Option<GameModeCommand>,
+}
+
+pub(crate) struct GameModeCommand {
+    pub(crate) command: String,
+    /// Set when the command passed a permission check.
+    pub(crate) permitted: bool,
+    /// Set when the game mode wrote its own audit record, with a target player or a failure.
+    pub(crate) audited: bool,
 }
 
 impl HQMServerPlayersAndMessages {
-    fn new(puck_slots: usize) -> Self {
+    pub(crate) fn new(puck_slots: usize) -> Self {
         let mut players = Vec::with_capacity(64);
         for _ in 0..64 {
             players.push((0, None));
@@ -241,6 +325,8 @@ impl HQMServerPlayersAndMessages {
             persistent_messages: vec![],
             recording_messages: vec![],
             puck_slots,
+            teams_locked: false,
+            game_mode_command: None,
         }
     }
 
@@ -254,6 +340,9 @@ impl HQMServerPlayersAndMessages {
             let player_index = PlayerIndex(player_index);
             if let Some(player) = p {
                 player.reset(player_index);
+                if let ServerPlayerData::Console { .. } = player.data {
+                    continue;
+                }
                 let update = player.get_update_message(player_index);
                 messages.push((update, true, true));
             }
@@ -360,10 +449,16 @@ impl HQMServerPlayersAndMessages {
                 None
             };
             if let Some(team) = team {
-                info!(
-                    "{} ({}) to team {}: {}",
-                    &player.player_name, sender_id, team, message
-                );
+                if player.is_muted == MuteStatus::Muted {
+                    return;
+                }
+                let shadow_muted = player.is_muted == MuteStatus::ShadowMuted;
+                if !shadow_muted {
+                    info!(
+                        "{} ({}) to team {}: {}",
+                        &player.player_name, sender_id, team, message
+                    );
+                }
                 let object = player
                     .object
                     .as_ref()
@@ -393,7 +488,10 @@ impl HQMServerPlayersAndMessages {
                     message: Cow::Owned(message.to_owned()),
                 });
 
-                for (_, player) in self.players.iter_players_mut() {
+                for (player_id, player) in self.players.iter_players_mut() {
+                    if shadow_muted && player_id != sender_id {
+                        continue;
+                    }
                     if player.team().is_some_and(|t| t == team) {
                         player.add_message(change1.clone());
                         player.add_message(chat.clone());
@@ -461,6 +559,39 @@ impl HQMServerPlayersAndMessages {
         false
     }
 
+    /// Increases the number of puck slots during a game. Skaters in the new puck slots are moved
+    /// to free object slots. Returns false if there are not enough free object slots.
+    fn add_puck_slots(&mut self, puck_slots: usize) -> bool {
+        if puck_slots <= self.puck_slots {
+            return true;
+        }
+        let mut used = 0u64;
+        let mut moving = vec![];
+        for (player_id, player) in self.players.iter_players() {
+            if let Some((object_index, _, _)) = &player.object {
+                used |= 1 << object_index;
+                if *object_index < puck_slots {
+                    moving.push(player_id);
+                }
+            }
+        }
+        let free: Vec<usize> = (puck_slots..32).filter(|i| (used >> i) & 1 == 0).collect();
+        if free.len() < moving.len() {
+            return false;
+        }
+        self.puck_slots = puck_slots;
+        for (player_id, new_object_index) in moving.into_iter().zip(free) {
+            if let Some(player) = self.players.get_player_mut(player_id) {
+                if let Some((object_index, _, _)) = &mut player.object {
+                    *object_index = new_object_index;
+                }
+                let update = player.get_update_message(player_id.index);
+                self.add_global_message(update, true, true);
+            }
+        }
+        true
+    }
+
     fn find_empty_player_object_slot(&self) -> Option<usize> {
         let mut v = 0u64;
         for object_index in self
@@ -480,7 +611,7 @@ impl HQMServerPlayersAndMessages {
         None
     }
 
-    fn add_player(&mut self, player_name: &str, addr: SocketAddr) -> Option<PlayerId> {
+    pub(crate) fn add_player(&mut self, player_name: &str, addr: SocketAddr) -> Option<PlayerId> {
         if self.players.find_player_by_addr(addr).is_some() {
             return None;
         }
@@ -502,6 +633,7 @@ impl HQMServerPlayersAndMessages {
                 };
 
                 self.add_global_message(update, true, true);
+                self.update_console_player_names();
 
                 Some(player_id)
             }
@@ -523,6 +655,7 @@ impl HQMServerPlayersAndMessages {
                 };
 
                 self.add_global_message(update, true, true);
+                self.update_console_player_names();
 
                 Some(player_id)
             }
@@ -530,6 +663,44 @@ impl HQMServerPlayersAndMessages {
         }
     }
 
+    fn add_console(&mut self, output: ConsoleOutput) -> Option<PlayerId> {
+        let player_index = find_empty_player_slot(&self.players);
+        match player_index {
+            Some(player_index) => {
+                // The console is never announced to the clients, it only exists so that
+                // console commands can be handled like commands from an administrator
+                self.players[player_index.0].1 = Some(HQMServerPlayer::new_console(output));
+                let player_id = PlayerId {
+                    index: player_index,
+                    gen: self.players[player_index.0].0,
+                };
+                self.update_console_player_names();
+
+                Some(player_id)
+            }
+            _ => None,
+        }
+    }
+
+    fn update_console_player_names(&self) {
+        let output = self.players.iter_players().find_map(|(_, player)| {
+            if let ServerPlayerData::Console { output } = &player.data {
+                Some(output)
+            } else {
+                None
+            }
+        });
+        if let Some(output) = output {
+            let names = self
+                .players
+                .iter_players()
+                .filter(|(_, player)| !matches!(player.data, ServerPlayerData::Console { .. }))
+                .map(|(_, player)| player.player_name.to_string())
+                .collect();
+            output.set_player_names(names);
+        }
+    }
+
     pub fn remove_player(&mut self, player_id: PlayerId, on_recording: bool) -> bool {
         if let Some(_) = self.players.get_player(player_id) {
             let update = HQMMessage::PlayerUpdate {
@@ -541,6 +712,7 @@ impl HQMServerPlayersAndMessages {
             self.players[player_id.index.0].1 = None;
 
             self.add_global_message(update, true, on_recording);
+            self.update_console_player_names();
 
             true
         } else {
@@ -617,7 +789,7 @@ pub(crate) struct HQMServerState {
     pub scoreboard: ScoreboardValues,
 
     packet: u32,
-    recording_data: BytesMut,
+    pub(crate) recording_data: BytesMut,
     recording_msg_pos: usize,
     recording_last_packet: u32,
 
@@ -647,6 +819,18 @@ impl HQMServerState {
         }
     }
 
+    /// Increases the number of puck slots without starting a new game.
+    /// Returns false if there are not enough free object slots.
+    pub(crate) fn add_puck_slots(&mut self, puck_slots: usize) -> bool {
+        if !self.players.add_puck_slots(puck_slots) {
+            return false;
+        }
+        if puck_slots > self.pucks.len() {
+            self.pucks.resize(puck_slots, None);
+        }
+        true
+    }
+
     fn new_game(&mut self, puck_slots: usize, scoreboard: ScoreboardValues) {
         self.players.new_game(puck_slots);
 
@@ -688,7 +872,18 @@ pub(crate) struct HQMServer {
     has_current_game_been_active: bool,
 
     pub(crate) ban: Box<dyn BanCheck>,
+    /// If set, only players on the allowlist can join.
+    allowlist: Option<Allowlist>,
+    pub(crate) name_bans: NameBanList,
     pub(crate) save_recording: Box<dyn RecordingSaveMethod>,
+
+    console_player_id: Option<PlayerId>,
+    pub(crate) votes: VoteState,
+    pub(crate) announcements: AnnouncementState,
+    pub(crate) warnings: WarningState,
+    pub(crate) restart: RestartState,
+    pub(crate) afk: AfkState,
+    pub(crate) flood: FloodState,
 }
 
 impl HQMServer {
@@ -697,8 +892,11 @@ impl HQMServer {
         config: ServerConfiguration,
         physics_config: PhysicsConfiguration,
         ban: Box<dyn BanCheck>,
+        allowlist: Option<Allowlist>,
+        name_bans: NameBanList,
         save_recording: Box<dyn RecordingSaveMethod>,
     ) -> Self {
+        let announcements = AnnouncementState::new(&config.announcements);
         let server = HQMServer {
             state: HQMServerState::new(initial_values.puck_slots, initial_values.values),
             allow_join: true,
@@ -710,7 +908,16 @@ impl HQMServer {
 
             has_current_game_been_active: false,
             ban,
+            allowlist,
+            name_bans,
             save_recording,
+            console_player_id: None,
+            votes: VoteState::default(),
+            announcements,
+            warnings: WarningState::default(),
+            restart: RestartState::default(),
+            afk: AfkState::default(),
+            flood: FloodState::default(),
 
             start_time: Default::default(),
             rink: Rink::new(30.0, 61.0, 8.5),
@@ -731,7 +938,9 @@ impl HQMServer {
                 version,
                 player_name,
             } => {
-                self.player_join(addr, version, player_name, behaviour);
+                if self.check_packet_flood(addr.ip()) {
+                    self.player_join(addr, version, player_name, behaviour);
+                }
             }
             HQMClientToServerMessage::Update {
                 current_game_id,
@@ -754,8 +963,10 @@ impl HQMServer {
             ),
             HQMClientToServerMessage::Exit => self.player_exit(addr, behaviour),
             HQMClientToServerMessage::ServerInfo { version, ping } => {
-                self.request_info(socket, addr, version, ping, behaviour, write_buf)
-                    .await;
+                if self.check_packet_flood(addr.ip()) {
+                    self.request_info(socket, addr, version, ping, behaviour, write_buf)
+                        .await;
+                }
             }
         }
     }
@@ -790,12 +1001,13 @@ impl HQMServer {
         let _ = socket.send_to(slice, addr).await;
     }
 
-    fn real_player_count(&self) -> usize {
+    pub(crate) fn real_player_count(&self) -> usize {
         let mut player_count = 0;
         for (_, player) in self.state.players.players.iter_players() {
             let is_actual_player = match player.data {
                 ServerPlayerData::NetworkPlayer { .. } => true,
                 ServerPlayerData::Bot { .. } => false,
+                ServerPlayerData::Console { .. } => false,
             };
             if is_actual_player {
                 player_count += 1;
@@ -822,6 +1034,8 @@ impl HQMServer {
                 return;
             }
         };
+        let input_changed = player.input != input;
+        player.set_input(input);
         if let ServerPlayerData::NetworkPlayer { data } = &mut player.data {
             let time_received = Instant::now();
 
@@ -847,7 +1061,9 @@ impl HQMServer {
             data.inactivity = 0;
             data.client_version = client_version;
             data.known_packet = new_known_packet;
-            player.input = input;
+            if input_changed {
+                data.afk_ticks = 0;
+            }
             data.game_id = current_game_id;
             data.known_msgpos = known_msgpos;
 
@@ -871,11 +1087,6 @@ impl HQMServer {
         name: String,
         behaviour: &mut B,
     ) {
-        let player_count = self.real_player_count();
-        let max_player_count = self.config.player_max;
-        if player_count >= max_player_count {
-            return; // Ignore join request
-        }
         if player_version != 55 {
             return; // Not the right version
         }
@@ -888,13 +1099,34 @@ impl HQMServer {
         if self.ban.check_ip_banned(addr.ip()) != BanCheckResponse::Allowed {
             return;
         }
+        if !self.is_name_allowed(&name, addr.ip()) {
+            return;
+        }
+
+        // Private server
+        if self
+            .allowlist
+            .as_ref()
+            .is_some_and(|allowlist| !allowlist.is_allowed(&name, addr.ip()))
+        {
+            return;
+        }
 
         // Disabled join
         if !self.allow_join {
             return;
         }
 
+        if !self.check_join_limits(addr.ip()) {
+            return;
+        }
+
+        if !self.make_room_for_player(&name, addr.ip(), behaviour) {
+            return; // Ignore join request
+        }
+
         if let Some(player_index) = self.add_player(&name, addr) {
+            self.record_join(addr.ip());
             behaviour.after_player_join(self.into(), player_index);
             info!(
                 "{} ({}) joined server from address {:?}",
@@ -937,8 +1169,11 @@ impl HQMServer {
                 self.set_allow_join(player_id, false);
             }
             "mute" => {
-                if let Ok(mute_player_index) = arg.parse::<PlayerIndex>() {
-                    self.mute_player(player_id, mute_player_index);
+                let (index, minutes) = arg.split_once(' ').unwrap_or((arg, ""));
+                if let (Ok(mute_player_index), Some(duration)) =
+                    (index.parse::<PlayerIndex>(), parse_mute_duration(minutes))
+                {
+                    self.mute_player(player_id, mute_player_index, duration);
                 }
             }
             "unmute" => {
@@ -946,13 +1181,14 @@ impl HQMServer {
                     self.unmute_player(player_id, mute_player_index);
                 }
             }
-            /*"shadowmute" => {
-                if let Ok(mute_player_index) = arg.parse::<usize>() {
-                    if mute_player_index < self.players.len() {
-                        self.shadowmute_player(player_index, mute_player_index);
-                    }
+            "shadowmute" => {
+                let (index, minutes) = arg.split_once(' ').unwrap_or((arg, ""));
+                if let (Ok(mute_player_index), Some(duration)) =
+                    (index.parse::<PlayerIndex>(), parse_mute_duration(minutes))
+                {
+                    self.shadowmute_player(player_id, mute_player_index, duration);
                 }
-            },*/
+            }
             "mutechat" => {
                 self.mute_chat(player_id);
             }
@@ -961,23 +1197,66 @@ impl HQMServer {
             }
             "kick" => {
                 if let Ok(kick_player_index) = arg.parse::<PlayerIndex>() {
-                    self.kick_player(player_id, kick_player_index, false, behaviour);
+                    self.kick_player(player_id, kick_player_index, false, None, "", behaviour);
                 }
             }
             "kickall" => {
                 self.kick_all_matching(player_id, arg, false, behaviour);
             }
             "ban" => {
-                if let Ok(kick_player_index) = arg.parse::<PlayerIndex>() {
-                    self.kick_player(player_id, kick_player_index, true, behaviour);
+                let (index, rest) = arg.split_once(' ').unwrap_or((arg, ""));
+                let rest = rest.trim();
+                // The duration is optional, a ban without it is permanent
+                let (duration, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+                let (duration, reason) = match parse_ban_duration(duration) {
+                    Some(duration) => (Some(duration), reason.trim()),
+                    None => (None, rest),
+                };
+                if let Ok(kick_player_index) = index.parse::<PlayerIndex>() {
+                    self.kick_player(
+                        player_id,
+                        kick_player_index,
+                        true,
+                        duration,
+                        reason,
+                        behaviour,
+                    );
                 }
             }
             "banall" => {
                 self.kick_all_matching(player_id, arg, true, behaviour);
             }
+            "banname" => {
+                self.ban_name(player_id, arg, behaviour);
+            }
+            "unbanname" => {
+                self.unban_name(player_id, arg);
+            }
+            "namebans" => {
+                self.list_name_bans(player_id);
+            }
+            "banrange" => {
+                self.ban_range(player_id, arg, behaviour);
+            }
+            "warn" => {
+                let (index, reason) = arg.split_once(' ').unwrap_or((arg, ""));
+                if let Ok(warn_player_index) = index.parse::<PlayerIndex>() {
+                    self.warn_player(player_id, warn_player_index, reason.trim(), behaviour);
+                }
+            }
             "clearbans" => {
                 self.clear_bans(player_id);
             }
+            "unban" => {
+                self.unban(player_id, arg);
+            }
+            "bans" => {
+                if arg.is_empty() {
+                    self.list_bans(player_id, 1);
+                } else if let Ok(page) = arg.parse::<usize>() {
+                    self.list_bans(player_id, page);
+                }
+            }
             "replay" | "record" => self.set_recording(player_id, arg),
             "lefty" => {
                 self.set_hand(SkaterHand::Left, player_id);
@@ -988,8 +1267,48 @@ impl HQMServer {
             "admin" => {
                 self.admin_login(player_id, arg);
             }
+            "votekick" => {
+                if let Some(target_id) = self.player_vote_target(arg) {
+                    self.start_player_vote(player_id, VoteKind::Kick(target_id), behaviour);
+                }
+            }
+            "votemute" => {
+                if let Some(target_id) = self.player_vote_target(arg) {
+                    self.start_player_vote(player_id, VoteKind::Mute(target_id), behaviour);
+                }
+            }
+            "votereset" => {
+                self.start_player_vote(player_id, VoteKind::Game(GameVote::Reset), behaviour);
+            }
+            "votestart" => {
+                self.start_player_vote(player_id, VoteKind::Game(GameVote::Start), behaviour);
+            }
+            "yes" => {
+                self.cast_vote(player_id);
+            }
+            "tp" | "teleport" => {
+                self.teleport_player(player_id, arg);
+            }
+            "team" => {
+                self.set_player_team(player_id, arg);
+            }
+            "lockteams" => {
+                self.lock_teams(player_id, true);
+            }
+            "unlockteams" => {
+                self.lock_teams(player_id, false);
+            }
+            "freeze" => {
+                self.freeze_player(player_id, arg, true);
+            }
+            "unfreeze" => {
+                self.freeze_player(player_id, arg, false);
+            }
+            "physics" => {
+                self.physics_command(player_id, arg);
+            }
             "serverrestart" => {
-                self.restart_server(player_id);
+                self.restart_server(player_id, arg);
             }
             "list" => {
                 if arg.is_empty() {
@@ -1097,19 +1416,55 @@ impl HQMServer {
                     .add_directed_server_chat_message(s, player_id);
             }
 
-            _ => behaviour.handle_command(self.into(), command, arg, player_id),
+            _ => {
+                self.state.players.game_mode_command = Some(GameModeCommand {
+                    command: command.to_owned(),
+                    permitted: false,
+                    audited: false,
+                });
+                behaviour.handle_command(self.into(), command, arg, player_id);
+                if let Some(GameModeCommand {
+                    permitted: true,
+                    audited: false,
+                    ..
+                }) = self.state.players.game_mode_command.take()
+                {
+                    self.audit(player_id, command, arg, None, Ok(()));
+                }
+            }
         }
     }
 
     fn list_players(&mut self, receiver_id: PlayerId, first_index: usize) {
+        let show_warnings = self
+            .state
+            .players
+            .players
+            .get_player(receiver_id)
+            .is_some_and(|player| player.is_admin());
         let res: Vec<_> = self
             .state
             .players
             .players
             .iter_players()
-            .filter(|(x, _)| x.index.0 >= first_index)
+            .filter(|(x, player)| {
+                x.index.0 >= first_index && !matches!(player.data, ServerPlayerData::Console { .. })
+            })
             .take(5)
-            .map(|(player_index, player)| format!("{}: {}", player_index.index, player.player_name))
+            .map(|(player_index, player)| {
+                let warnings = match player.ip() {
+                    Some(ip) if show_warnings => self.warnings.count(&player.player_name, ip),
+                    _ => 0,
+                };
+                if warnings > 0 {
+                    format!(
+                        "{}: {} ({} warnings)",
+                        player_index.index, player.player_name, warnings
+                    )
+                } else {
+                    format!("{}: {}", player_index.index, player.player_name)
+                }
+            })
             .collect();
         for msg in res {
             self.state
@@ -1214,6 +1569,9 @@ impl HQMServer {
     pub fn player_exact_unique_match(&self, name: &str) -> Option<(PlayerId, Rc<str>)> {
         let mut found = None;
         for (player_id, player) in self.state.players.players.iter_players() {
+            if matches!(player.data, ServerPlayerData::Console { .. }) {
+                continue;
+            }
             if player.player_name.as_ref() == name {
                 if found.is_none() {
                     found = Some((player_id, player.player_name.clone()));
@@ -1229,6 +1587,9 @@ impl HQMServer {
         let name = name.to_lowercase();
         let mut found = Vec::new();
         for (player_index, player) in self.state.players.players.iter_players() {
+            if matches!(player.data, ServerPlayerData::Console { .. }) {
+                continue;
+            }
             if player.player_name.to_lowercase().contains(&name) {
                 found.push((player_index, player.player_name.clone()));
                 if found.len() >= 5 {
@@ -1274,6 +1635,35 @@ impl HQMServer {
         }
     }
 
+    /// Records a privileged action in the audit log.
+    pub(crate) fn audit(
+        &self,
+        admin_player_id: PlayerId,
+        command: &str,
+        args: &str,
+        target_player_id: Option<PlayerId>,
+        result: Result<(), &str>,
+    ) {
+        let players = &self.state.players.players;
+        let Some(admin) = players.get_player(admin_player_id) else {
+            return;
+        };
+        let target = target_player_id.and_then(|id| players.get_player(id).map(|x| (id, x)));
+        audit_log(admin_player_id, admin, command, args, target, result);
+    }
+
+    fn console_message<B: GameMode>(&mut self, msg: String, behaviour: &mut B) {
+        if let Some(console_player_id) = self.console_player_id {
+            if msg.starts_with("/") {
+                info!("Console command: {}", msg);
+                self.process_message(msg, console_player_id, behaviour);
+            } else {
+                info!("Console: {}", msg);
+                self.state.players.add_server_chat_message(msg);
+            }
+        }
+    }
+
     fn player_exit<B: GameMode>(&mut self, addr: SocketAddr, behaviour: &mut B) {
         let player = self.state.players.players.find_player_by_addr(addr);
 
@@ -1303,12 +1693,9 @@ impl HQMServer {
     pub fn remove_player(&mut self, player_id: PlayerId, on_recording: bool) -> bool {
         let res = self.state.players.remove_player(player_id, on_recording);
         if res {
-            let admin_found = self
-                .state
-                .players
-                .players
-                .iter_players()
-                .any(|(_, x)| x.is_admin);
+            let admin_found = self.state.players.players.iter_players().any(|(_, x)| {
+                x.is_admin() && matches!(x.data, ServerPlayerData::NetworkPlayer { .. })
+            });
 
             if !admin_found {
                 self.allow_join = true;
@@ -1374,6 +1761,9 @@ impl HQMServer {
             .iter_players_mut()
             .filter_map(|(player_id, player)| {
                 if let ServerPlayerData::NetworkPlayer { data } = &mut player.data {
+                    if player.object.is_some() {
+                        data.last_on_ice = Instant::now();
+                    }
                     data.inactivity += 1;
                     if data.inactivity > 500 {
                         Some((player_id, player.player_name.clone()))
@@ -1410,6 +1800,10 @@ impl HQMServer {
 
             let (game_step, forced_view) = tokio::task::block_in_place(|| {
                 self.remove_inactive_players(behaviour);
+                self.update_vote(behaviour);
+                self.update_mutes();
+                self.update_afk_players();
+                self.update_announcements();
 
                 behaviour.before_tick(self.into());
 
@@ -1453,7 +1847,7 @@ impl HQMServer {
         }
     }
 
-    fn save_recording(&mut self, old_recording_data: &[u8]) {
+    pub(crate) fn save_recording(&mut self, old_recording_data: &[u8]) {
         let size = old_recording_data.len();
         let mut recording_data = BytesMut::with_capacity(size + 8);
         recording_data.put_u32_le(0u32);
@@ -1625,7 +2019,18 @@ async fn send_updates(
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+/// Parses the optional duration in minutes of a mute command.
+fn parse_mute_duration(minutes: &str) -> Option<Option<Duration>> {
+    let minutes = minutes.trim();
+    if minutes.is_empty() {
+        Some(None)
+    } else {
+        let minutes = minutes.parse::<u64>().ok().filter(|x| *x > 0)?;
+        Some(Some(Duration::from_secs(minutes.checked_mul(60)?)))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MuteStatus {
     NotMuted,
     ShadowMuted,
@@ -1644,11 +2049,34 @@ pub(crate) struct NetworkPlayerData {
     pub(crate) view_player_index: PlayerIndex,
     pub game_id: u32,
     pub(crate) messages: Vec<Rc<HQMMessage>>,
+    pub(crate) last_on_ice: Instant,
+    /// Ticks with running clock since the input last changed.
+    pub(crate) afk_ticks: u32,
 }
 
 pub(crate) enum ServerPlayerData {
     NetworkPlayer { data: NetworkPlayerData },
     Bot {},
+    Console { output: ConsoleOutput },
+}
+
+/// A team change requested by an administrator with /team, or by the server for AFK players.
+/// The game mode carries it out the next time it adds players to the teams, so team sizes
+/// are still enforced.
+#[derive(Clone)]
+pub(crate) struct TeamRequest {
+    /// `None` moves the player to the spectators.
+    pub team: Option<Team>,
+    pub source: TeamRequestSource,
+}
+
+#[derive(Clone)]
+pub(crate) enum TeamRequestSource {
+    Admin {
+        player_id: PlayerId,
+        player_name: Rc<str>,
+    },
+    Afk,
 }
 
 pub(crate) struct HQMServerPlayer {
@@ -1657,10 +2085,14 @@ pub(crate) struct HQMServerPlayer {
     player_name_blue: Rc<str>,
     pub(crate) object: Option<(usize, SkaterObject, Team)>,
     pub data: ServerPlayerData,
-    pub is_admin: bool,
+    pub admin: Option<AdminLogin>,
     pub is_muted: MuteStatus,
+    pub mute_expires: Option<Instant>,
+    /// Input from frozen players is ignored.
+    pub is_frozen: bool,
     pub preferred_hand: SkaterHand,
     pub input: PlayerInput,
+    pub(crate) team_request: Option<TeamRequest>,
 }
 
 impl HQMServerPlayer {
@@ -1689,12 +2121,17 @@ impl HQMServerPlayer {
                     view_player_index: player_index,
                     game_id: u32::MAX,
                     messages: global_messages.into_iter().cloned().collect(),
+                    last_on_ice: Instant::now(),
+                    afk_ticks: 0,
                 },
             },
-            is_admin: false,
+            admin: None,
             input: Default::default(),
             is_muted: MuteStatus::NotMuted,
+            mute_expires: None,
+            is_frozen: false,
             preferred_hand: SkaterHand::Right,
+            team_request: None,
         }
     }
 
@@ -1705,15 +2142,36 @@ impl HQMServerPlayer {
             player_name_blue: format!("[Blue] {}", player_name).into(),
             object: None,
             data: ServerPlayerData::Bot {},
-            is_admin: false,
+            admin: None,
+            input: Default::default(),
+            is_muted: MuteStatus::NotMuted,
+            mute_expires: None,
+            is_frozen: false,
+            preferred_hand: SkaterHand::Right,
+            team_request: None,
+        }
+    }
+
+    fn new_console(output: ConsoleOutput) -> Self {
+        HQMServerPlayer {
+            player_name: "Console".into(),
+            player_name_red: "[Red] Console".into(),
+            player_name_blue: "[Blue] Console".into(),
+            object: None,
+            data: ServerPlayerData::Console { output },
+            admin: Some(AdminLogin::full_access("console")),
             input: Default::default(),
             is_muted: MuteStatus::NotMuted,
+            mute_expires: None,
+            is_frozen: false,
             preferred_hand: SkaterHand::Right,
+            team_request: None,
         }
     }
 
     fn reset(&mut self, player_index: PlayerIndex) {
         self.object = None;
+        self.team_request = None;
         if let ServerPlayerData::NetworkPlayer { data } = &mut self.data {
             data.known_msgpos = 0;
             data.known_packet = u32::MAX;
@@ -1735,6 +2193,32 @@ impl HQMServerPlayer {
         }
     }
 
+    /// Freezing also drops the current input, so the skater stops steering.
+    pub(crate) fn set_frozen(&mut self, frozen: bool) {
+        self.is_frozen = frozen;
+        if frozen {
+            self.input = Default::default();
+        }
+    }
+
+    fn set_input(&mut self, input: PlayerInput) {
+        if !self.is_frozen {
+            self.input = input;
+        }
+    }
+
+    /// Lifts a timed mute that has expired. Returns the mute status the player had.
+    pub(crate) fn lift_expired_mute(&mut self, now: Instant) -> Option<MuteStatus> {
+        if self.mute_expires.is_some_and(|x| x <= now) {
+            let old_status = self.is_muted;
+            self.is_muted = MuteStatus::NotMuted;
+            self.mute_expires = None;
+            Some(old_status)
+        } else {
+            None
+        }
+    }
+
     fn add_message(&mut self, message: Rc<HQMMessage>) {
         match &mut self.data {
             ServerPlayerData::NetworkPlayer {
@@ -1775,7 +2259,7 @@ impl HQMServerPlayer {
                     deviation: dev,
                 })
             }
-            ServerPlayerData::Bot { .. } => None,
+            ServerPlayerData::Bot { .. } | ServerPlayerData::Console { .. } => None,
         }
     }
 
@@ -1799,6 +2283,10 @@ impl HQMServerPlayer {
         message: impl Into<Cow<'static, str>>,
         sender_index: Option<PlayerIndex>,
     ) {
+        if let ServerPlayerData::Console { output } = &self.data {
+            output.send(&message.into());
+            return;
+        }
         let chat = HQMMessage::Chat {
             player_index: sender_index,
             message: message.into(),
@@ -1813,6 +2301,24 @@ impl HQMServerPlayer {
     pub fn has_skater(&self) -> bool {
         self.object.is_some()
     }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin.is_some()
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        if let ServerPlayerData::NetworkPlayer { data } = &self.data {
+            Some(data.addr.ip())
+        } else {
+            None
+        }
+    }
+
+    pub fn has_permission(&self, permission: AdminPermission) -> bool {
+        self.admin
+            .as_ref()
+            .is_some_and(|x| x.permissions.contains(permission))
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -1823,6 +2329,12 @@ struct PingData {
     pub deviation: f32,
 }
 
+enum Msg {
+    Time,
+    Message(SocketAddr, HQMClientToServerMessage),
+    Console(ConsoleCommand),
+}
+
 /// Starts an HQM server. This method will not return until the server has terminated.
 pub async fn run_server<B: GameMode>(
     port: u16,
@@ -1837,9 +2349,39 @@ pub async fn run_server<B: GameMode>(
 
     let reqwest_client = reqwest::Client::new();
 
-    let mut server = HQMServer::new(initial_values, config, physics_config, ban, recording);
+    let allowlist = if let Some(path) = &config.allowlist_file {
+        Some(
+            Allowlist::new(path.clone())
+                .await
+                .map_err(std::io::Error::other)?,
+        )
+    } else {
+        None
+    };
+
+    let name_bans = NameBanList::new(config.name_ban_file.clone()).await?;
+    let console = config.console.as_ref().and_then(ConsoleReceiver::take);
+
+    let mut server = HQMServer::new(
+        initial_values,
+        config,
+        physics_config,
+        ban,
+        allowlist,
+        name_bans,
+        recording,
+    );
     info!("Server started");
 
+    let console_stream = if let Some((commands, output)) = console {
+        server.console_player_id = server.state.players.add_console(output);
+        tokio_stream::wrappers::UnboundedReceiverStream::new(commands)
+            .map(Msg::Console)
+            .left_stream()
+    } else {
+        futures::stream::empty().right_stream()
+    };
+
     behaviour.init((&mut server).into());
 
     // Set up timers
@@ -1893,10 +2435,6 @@ pub async fn run_server<B: GameMode>(
             }
         });
     }
-    enum Msg {
-        Time,
-        Message(SocketAddr, HQMClientToServerMessage),
-    }
 
     let timeout_stream = tokio_stream::wrappers::IntervalStream::new(tick_timer).map(|_| Msg::Time);
     let packet_stream = {
@@ -1920,17 +2458,169 @@ pub async fn run_server<B: GameMode>(
     };
     tokio::pin!(packet_stream);
 
-    let mut stream = futures::stream_select!(timeout_stream, packet_stream);
+    let mut stream = futures::stream_select!(timeout_stream, packet_stream, console_stream);
     let mut write_buf = BytesMut::with_capacity(4096);
     while let Some(msg) = stream.next().await {
         match msg {
-            Msg::Time => server.tick(&socket, &mut behaviour, &mut write_buf).await,
+            Msg::Time => {
+                server.tick(&socket, &mut behaviour, &mut write_buf).await;
+                if server.update_restart() {
+                    server.restart().await;
+                }
+            }
             Msg::Message(addr, data) => {
                 server
                     .handle_message(addr, &socket, data, &mut behaviour, &mut write_buf)
                     .await
             }
+            Msg::Console(ConsoleCommand::Line(msg)) => server.console_message(msg, &mut behaviour),
+            Msg::Console(ConsoleCommand::Shutdown) => {
+                server.shutdown().await;
+                break;
+            }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{PlayerIndex, PlayerInput, Team};
+    use crate::server::{
+        parse_mute_duration, HQMMessage, HQMServerPlayer, HQMServerPlayersAndMessages, MuteStatus,
+        PlayerListExt, ServerPlayerData,
+    };
+    use nalgebra::{Point3, Rotation3};
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+
+    fn add_skaters(state: &mut HQMServerPlayersAndMessages, count: u16) {
+        for i in 0..count {
+            let addr = SocketAddr::from(([10, 0, 0, 1], 10000 + i));
+            let player_id = state.add_player(&format!("Player {}", i), addr).unwrap();
+            assert!(state.spawn_skater(
+                player_id,
+                Team::Red,
+                Point3::origin(),
+                Rotation3::identity(),
+                false
+            ));
+        }
+    }
+
+    /// Counts the chat messages a player has received.
+    fn chat_messages(state: &HQMServerPlayersAndMessages, index: usize) -> usize {
+        let (_, player) = state
+            .players
+            .get_player_by_index(PlayerIndex(index))
+            .unwrap();
+        match &player.data {
+            ServerPlayerData::NetworkPlayer { data } => data
+                .messages
+                .iter()
+                .filter(|x| matches!(***x, HQMMessage::Chat { .. }))
+                .count(),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn team_message_mute() {
+        let mut state = HQMServerPlayersAndMessages::new(1);
+        add_skaters(&mut state, 2);
+        let (sender_id, _) = state.players.get_player_by_index(PlayerIndex(0)).unwrap();
+
+        state.add_user_team_message("hello", sender_id);
+        assert_eq!(chat_messages(&state, 0), 1);
+        assert_eq!(chat_messages(&state, 1), 1);
+
+        // A shadowmuted player sees their own message, but nobody else does
+        state.players.get_player_mut(sender_id).unwrap().is_muted = MuteStatus::ShadowMuted;
+        state.add_user_team_message("hello", sender_id);
+        assert_eq!(chat_messages(&state, 0), 2);
+        assert_eq!(chat_messages(&state, 1), 1);
+
+        state.players.get_player_mut(sender_id).unwrap().is_muted = MuteStatus::Muted;
+        state.add_user_team_message("hello", sender_id);
+        assert_eq!(chat_messages(&state, 0), 2);
+        assert_eq!(chat_messages(&state, 1), 1);
+    }
+
+    #[test]
+    fn mute_expiry() {
+        let now = Instant::now();
+        let mut player = HQMServerPlayer::new_bot("Bot");
+        player.is_muted = MuteStatus::ShadowMuted;
+        player.mute_expires = Some(now + Duration::from_secs(60));
+        assert_eq!(player.lift_expired_mute(now), None);
+        assert_eq!(player.is_muted, MuteStatus::ShadowMuted);
+        assert_eq!(
+            player.lift_expired_mute(now + Duration::from_secs(60)),
+            Some(MuteStatus::ShadowMuted)
+        );
+        assert_eq!(player.is_muted, MuteStatus::NotMuted);
+        assert_eq!(player.mute_expires, None);
+
+        // Mutes without a duration never expire
+        player.is_muted = MuteStatus::Muted;
+        assert_eq!(
+            player.lift_expired_mute(now + Duration::from_secs(3600)),
+            None
+        );
+        assert_eq!(player.is_muted, MuteStatus::Muted);
+    }
+
+    #[test]
+    fn frozen_input() {
+        let input = PlayerInput {
+            fwbw: 1.0,
+            ..Default::default()
+        };
+        let mut player = HQMServerPlayer::new_bot("Bot");
+        player.set_input(input.clone());
+        assert_eq!(player.input, input);
+
+        player.set_frozen(true);
+        assert_eq!(player.input, PlayerInput::default());
+        player.set_input(input.clone());
+        assert_eq!(player.input, PlayerInput::default());
+
+        player.set_frozen(false);
+        player.set_input(input.clone());
+        assert_eq!(player.input, input);
+    }
+
+    #[test]
+    fn mute_duration() {
+        assert_eq!(parse_mute_duration(""), Some(None));
+        assert_eq!(
+            parse_mute_duration("5"),
+            Some(Some(Duration::from_secs(5 * 60)))
+        );
+        assert_eq!(parse_mute_duration("0"), None);
+        assert_eq!(parse_mute_duration("spam"), None);
+        assert_eq!(parse_mute_duration("999999999999999999"), None);
+    }
+
+    #[test]
+    fn add_puck_slots() {
+        let mut state = HQMServerPlayersAndMessages::new(1);
+        add_skaters(&mut state, 4);
+        assert!(state.add_puck_slots(4));
+        let mut object_indices: Vec<_> = state
+            .players
+            .iter_players()
+            .filter_map(|(_, player)| player.object.as_ref().map(|(i, _, _)| *i))
+            .collect();
+        object_indices.sort();
+        object_indices.dedup();
+        assert_eq!(object_indices.len(), 4);
+        assert!(object_indices.iter().all(|i| *i >= 4));
+
+        // Every object slot is taken
+        let mut state = HQMServerPlayersAndMessages::new(2);
+        add_skaters(&mut state, 30);
+        assert!(!state.add_puck_slots(3));
+        assert_eq!(state.puck_slots, 2);
+    }
+}
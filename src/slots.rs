@@ -0,0 +1,224 @@
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::game::PlayerId;
+use crate::gamemode::{ExitReason, GameMode};
+use crate::server::{HQMServer, PlayerListExt, ServerPlayerData, ServerStatePlayerItem};
+
+/// Reserved player slots and the spectator limit.
+#[derive(Debug, Clone)]
+pub struct SlotConfiguration {
+    /// Number of slots of player_max that only reserved players can use.
+    pub reserved_slots: usize,
+    /// Player names that can use reserved slots. Names are not verified when joining, so anyone
+    /// who knows a reserved name can use it.
+    pub reserved_names: Vec<String>,
+    /// IP addresses that can use reserved slots.
+    pub reserved_ips: Vec<IpAddr>,
+    /// Maximum number of spectators, or [None] if spectators are only limited by player_max.
+    pub spectator_max: Option<usize>,
+    /// Time a spectator must have been off the ice before they can be removed to make room.
+    pub spectator_idle_time: Duration,
+}
+
+impl Default for SlotConfiguration {
+    fn default() -> Self {
+        Self {
+            reserved_slots: 0,
+            reserved_names: vec![],
+            reserved_ips: vec![],
+            spectator_max: None,
+            spectator_idle_time: Duration::from_secs(120),
+        }
+    }
+}
+
+impl SlotConfiguration {
+    /// Returns true if a player with this name or IP address can use reserved slots.
+    ///
+    /// Administrators are not reserved players by their account name, because players are not
+    /// logged in when they join. Add their addresses to reserved_ips instead.
+    pub(crate) fn is_reserved(&self, player_name: &str, ip: IpAddr) -> bool {
+        self.reserved_ips.contains(&ip)
+            || self
+                .reserved_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(player_name))
+    }
+
+    /// Returns true if a new player fits without removing anyone.
+    fn has_room(
+        &self,
+        reserved: bool,
+        player_max: usize,
+        player_count: usize,
+        spectator_count: usize,
+    ) -> bool {
+        let player_max = if reserved {
+            player_max
+        } else {
+            player_max.saturating_sub(self.reserved_slots)
+        };
+        player_count < player_max
+            && (reserved || self.spectator_max.is_none_or(|max| spectator_count < max))
+    }
+
+    /// Finds the spectator that has been off the ice the longest, ignoring administrators and reserved players.
+    fn find_idle_spectator(
+        &self,
+        players: &[ServerStatePlayerItem],
+        now: Instant,
+    ) -> Option<(PlayerId, Rc<str>)> {
+        players
+            .iter_players()
+            .filter_map(|(player_id, player)| match &player.data {
+                ServerPlayerData::NetworkPlayer { data }
+                    if player.object.is_none()
+                        && !player.is_admin()
+                        && now - data.last_on_ice >= self.spectator_idle_time
+                        && !self.is_reserved(&player.player_name, data.addr.ip()) =>
+                {
+                    Some((player_id, player.player_name.clone(), data.last_on_ice))
+                }
+                _ => None,
+            })
+            .min_by_key(|(_, _, last_on_ice)| *last_on_ice)
+            .map(|(player_id, player_name, _)| (player_id, player_name))
+    }
+}
+
+impl HQMServer {
+    /// Checks if a new player can join. Reserved players can remove an idle spectator to make room.
+    pub(crate) fn make_room_for_player<B: GameMode>(
+        &mut self,
+        player_name: &str,
+        ip: IpAddr,
+        behaviour: &mut B,
+    ) -> bool {
+        let config = &self.config.slots;
+        let reserved = config.is_reserved(player_name, ip);
+        let spectator_count = self
+            .state
+            .players
+            .players
+            .iter_players()
+            .filter(|(_, player)| {
+                player.object.is_none()
+                    && matches!(player.data, ServerPlayerData::NetworkPlayer { .. })
+            })
+            .count();
+
+        let player_count = self.real_player_count();
+        if config.has_room(
+            reserved,
+            self.config.player_max,
+            player_count,
+            spectator_count,
+        ) {
+            return true;
+        }
+        if !reserved {
+            return false;
+        }
+
+        let Some((evict_player_id, evict_player_name)) =
+            config.find_idle_spectator(&self.state.players.players, Instant::now())
+        else {
+            return false;
+        };
+        behaviour.before_player_exit(self.into(), evict_player_id, ExitReason::Evicted);
+        self.remove_player(evict_player_id, true);
+        info!(
+            "{} ({}) removed to make room for {}",
+            evict_player_name, evict_player_id, player_name
+        );
+        let msg = format!(
+            "{} removed to make room for another player",
+            evict_player_name
+        );
+        self.state.players.add_server_chat_message(msg);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{PlayerIndex, Team};
+    use crate::server::{HQMServerPlayersAndMessages, PlayerListExt, ServerPlayerData};
+    use crate::slots::SlotConfiguration;
+    use nalgebra::{Point3, Rotation3};
+    use std::net::{IpAddr, SocketAddr};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn reserved_player() {
+        let config = SlotConfiguration {
+            reserved_names: vec!["Goalie".to_owned()],
+            reserved_ips: vec!["10.0.0.1".parse().unwrap()],
+            ..Default::default()
+        };
+        let other_ip: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(config.is_reserved("goalie", other_ip));
+        assert!(config.is_reserved("Someone", "10.0.0.1".parse().unwrap()));
+        assert!(!config.is_reserved("Someone", other_ip));
+    }
+
+    #[test]
+    fn slot_room() {
+        let config = SlotConfiguration {
+            reserved_slots: 2,
+            spectator_max: Some(3),
+            ..Default::default()
+        };
+        // 8 of 10 slots are open to everyone
+        assert!(config.has_room(false, 10, 7, 0));
+        assert!(!config.has_room(false, 10, 8, 0));
+        assert!(config.has_room(true, 10, 9, 0));
+        assert!(!config.has_room(true, 10, 10, 0));
+        // The spectator limit does not apply to reserved players
+        assert!(!config.has_room(false, 10, 5, 3));
+        assert!(config.has_room(true, 10, 5, 3));
+    }
+
+    #[test]
+    fn idle_spectator() {
+        let now = Instant::now();
+        let config = SlotConfiguration {
+            reserved_names: vec!["Reserved".to_owned()],
+            spectator_idle_time: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut state = HQMServerPlayersAndMessages::new(1);
+        let names = ["Skater", "Recent", "Reserved", "Old", "Older"];
+        let off_ice = [600, 30, 900, 300, 400];
+        for (i, (name, secs)) in names.iter().zip(off_ice).enumerate() {
+            let addr = SocketAddr::from(([10, 0, 0, 1], 10000 + i as u16));
+            let player_id = state.add_player(name, addr).unwrap();
+            let player = state.players.get_player_mut(player_id).unwrap();
+            if let ServerPlayerData::NetworkPlayer { data } = &mut player.data {
+                data.last_on_ice = now - Duration::from_secs(secs);
+            }
+        }
+        let (skater_id, _) = state.players.get_player_by_index(PlayerIndex(0)).unwrap();
+        assert!(state.spawn_skater(
+            skater_id,
+            Team::Red,
+            Point3::origin(),
+            Rotation3::identity(),
+            false
+        ));
+
+        // Skaters, reserved players and recent spectators are never removed
+        let (_, player_name) = config.find_idle_spectator(&state.players, now).unwrap();
+        assert_eq!(player_name.as_ref(), "Older");
+
+        let config = SlotConfiguration {
+            spectator_idle_time: Duration::from_secs(1000),
+            ..config
+        };
+        assert!(config.find_idle_spectator(&state.players, now).is_none());
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::game::{PlayerId, PlayerIndex};
+use crate::gamemode::{ExitReason, GameMode, GameVote};
+use crate::server::{HQMServer, PlayerListExt, ServerPlayerData};
+
+/// Settings for player votes.
+#[derive(Debug, Clone)]
+pub struct VoteConfiguration {
+    pub enabled: bool,
+    /// Fraction of the players on the ice that must vote for a kick.
+    pub kick_threshold: f32,
+    /// Fraction of the players on the ice that must vote for a mute.
+    pub mute_threshold: f32,
+    /// Fraction of the players on the ice that must vote for a game reset.
+    pub reset_threshold: f32,
+    /// Fraction of the players on the ice that must vote for a game start.
+    pub start_threshold: f32,
+    /// Minimum number of votes for any vote to pass. A vote never passes with only the vote
+    /// of the player who started it.
+    pub min_voters: usize,
+    /// Time before a vote that has not passed is cancelled.
+    pub timeout: Duration,
+    /// Time a player has to wait after starting a vote before starting another one.
+    /// It applies to all players from the same IP address.
+    pub cooldown: Duration,
+}
+
+impl Default for VoteConfiguration {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            kick_threshold: 0.6,
+            mute_threshold: 0.5,
+            reset_threshold: 0.6,
+            start_threshold: 0.5,
+            min_voters: 3,
+            timeout: Duration::from_secs(60),
+            cooldown: Duration::from_secs(120),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VoteKind {
+    Kick(PlayerId),
+    Mute(PlayerId),
+    Game(GameVote),
+}
+
+struct ActiveVote {
+    kind: VoteKind,
+    description: String,
+    voters: HashSet<PlayerId>,
+    started: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct VoteState {
+    active: Option<ActiveVote>,
+    /// Keyed by IP address, so that rejoining does not reset the cooldown.
+    last_vote_started: HashMap<IpAddr, Instant>,
+}
+
+impl HQMServer {
+    pub(crate) fn start_player_vote<B: GameMode>(
+        &mut self,
+        player_id: PlayerId,
+        kind: VoteKind,
+        behaviour: &mut B,
+    ) {
+        if !self.config.vote.enabled {
+            self.state
+                .players
+                .add_directed_server_chat_message("Voting is disabled", player_id);
+            return;
+        }
+        if !self.can_vote(player_id) {
+            return;
+        }
+        if let Some(active) = &self.votes.active {
+            if active.kind == kind {
+                self.cast_vote(player_id);
+            } else {
+                self.state.players.add_directed_server_chat_message(
+                    "Another vote is already in progress",
+                    player_id,
+                );
+            }
+            return;
+        }
+
+        let description = match kind {
+            VoteKind::Kick(target_id) | VoteKind::Mute(target_id) => {
+                let Some(target) = self.state.players.players.get_player(target_id) else {
+                    return;
+                };
+                let msg = if target_id == player_id {
+                    Some("You cannot start a vote against yourself")
+                } else if target.is_admin() {
+                    Some("Administrators cannot be voted against")
+                } else if !matches!(target.data, ServerPlayerData::NetworkPlayer { .. }) {
+                    Some("You can only start a vote against a player")
+                } else {
+                    None
+                };
+                if let Some(msg) = msg {
+                    self.state
+                        .players
+                        .add_directed_server_chat_message(msg, player_id);
+                    return;
+                }
+                if matches!(kind, VoteKind::Kick(_)) {
+                    format!("kick {}", target.player_name)
+                } else {
+                    format!("mute {}", target.player_name)
+                }
+            }
+            VoteKind::Game(vote) => {
+                if !behaviour.supports_vote(vote) {
+                    self.state.players.add_directed_server_chat_message(
+                        "That vote is not available in this game mode",
+                        player_id,
+                    );
+                    return;
+                }
+                match vote {
+                    GameVote::Reset => "reset the game".to_owned(),
+                    GameVote::Start => "start the game".to_owned(),
+                }
+            }
+        };
+
+        let now = Instant::now();
+        let cooldown = self.config.vote.cooldown;
+        self.votes
+            .last_vote_started
+            .retain(|_, last| now - *last < cooldown);
+        let ip = self
+            .state
+            .players
+            .players
+            .get_player(player_id)
+            .and_then(|player| player.ip());
+        if let Some(last) = ip.and_then(|ip| self.votes.last_vote_started.get(&ip)) {
+            let wait = cooldown.saturating_sub(now - *last);
+            if !wait.is_zero() {
+                let msg = format!(
+                    "You must wait {} seconds before starting another vote",
+                    wait.as_secs() + 1
+                );
+                self.state
+                    .players
+                    .add_directed_server_chat_message(msg, player_id);
+                return;
+            }
+        }
+        if let Some(ip) = ip {
+            self.votes.last_vote_started.insert(ip, now);
+        }
+
+        let Some(player) = self.state.players.players.get_player(player_id) else {
+            return;
+        };
+        info!(
+            "{} ({}) started a vote to {}",
+            player.player_name, player_id, description
+        );
+        let msg = format!(
+            "{} started a vote to {}, {} votes needed. Type /yes to vote",
+            player.player_name,
+            description,
+            self.required_votes(kind)
+        );
+        self.state.players.add_server_chat_message(msg);
+
+        self.votes.active = Some(ActiveVote {
+            kind,
+            description,
+            voters: HashSet::from([player_id]),
+            started: now,
+        });
+        self.update_vote(behaviour);
+    }
+
+    pub(crate) fn cast_vote(&mut self, player_id: PlayerId) {
+        if !self.can_vote(player_id) {
+            return;
+        }
+        let Some(active) = &mut self.votes.active else {
+            self.state
+                .players
+                .add_directed_server_chat_message("There is no vote in progress", player_id);
+            return;
+        };
+        let players = &self.state.players.players;
+        let ip = players.get_player(player_id).and_then(|player| player.ip());
+        let already_voted = active.voters.contains(&player_id)
+            || ip.is_some_and(|ip| {
+                active.voters.iter().any(|voter_id| {
+                    players
+                        .get_player(*voter_id)
+                        .is_some_and(|voter| voter.ip() == Some(ip))
+                })
+            });
+        if already_voted {
+            self.state
+                .players
+                .add_directed_server_chat_message("You have already voted", player_id);
+            return;
+        }
+        active.voters.insert(player_id);
+        let kind = active.kind;
+        let votes = self.current_votes();
+        let required = self.required_votes(kind);
+        if let Some(player) = self.state.players.players.get_player(player_id) {
+            let msg = format!("{} voted yes ({}/{})", player.player_name, votes, required);
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
+    /// Checks if the current vote has passed or timed out. Called every tick.
+    pub(crate) fn update_vote<B: GameMode>(&mut self, behaviour: &mut B) {
+        let Some(active) = &self.votes.active else {
+            return;
+        };
+        let kind = active.kind;
+        let target_gone = match kind {
+            VoteKind::Kick(target_id) | VoteKind::Mute(target_id) => {
+                self.state.players.players.get_player(target_id).is_none()
+            }
+            VoteKind::Game(_) => false,
+        };
+        if target_gone {
+            info!("Vote to {} cancelled, the player left", active.description);
+            let msg = format!("Vote to {} cancelled, the player left", active.description);
+            self.votes.active = None;
+            self.state.players.add_server_chat_message(msg);
+            return;
+        }
+
+        if self.current_votes() >= self.required_votes(kind) {
+            let Some(active) = self.votes.active.take() else {
+                return;
+            };
+            info!("Vote to {} passed", active.description);
+            let msg = format!("Vote to {} passed", active.description);
+            self.state.players.add_server_chat_message(msg);
+            match kind {
+                VoteKind::Kick(target_id) => {
+                    self.kick_player_by(target_id, None, "vote", ExitReason::VoteKicked, behaviour);
+                }
+                VoteKind::Mute(target_id) => {
+                    self.mute_player_by(target_id, "vote", None);
+                }
+                VoteKind::Game(vote) => {
+                    behaviour.vote_passed(self.into(), vote);
+                }
+            }
+        } else if active.started.elapsed() > self.config.vote.timeout {
+            info!("Vote to {} failed", active.description);
+            let msg = format!("Vote to {} failed", active.description);
+            self.votes.active = None;
+            self.state.players.add_server_chat_message(msg);
+        }
+    }
+
+    /// Only players on the ice can vote.
+    fn can_vote(&mut self, player_id: PlayerId) -> bool {
+        let on_ice = self
+            .state
+            .players
+            .players
+            .get_player(player_id)
+            .is_some_and(|player| player.object.is_some());
+        if !on_ice {
+            self.state
+                .players
+                .add_directed_server_chat_message("Only players on the ice can vote", player_id);
+        }
+        on_ice
+    }
+
+    /// Counts the voters that are still on the ice, at most one per IP address.
+    fn current_votes(&self) -> usize {
+        let Some(active) = &self.votes.active else {
+            return 0;
+        };
+        let players = &self.state.players.players;
+        count_distinct_ips(active.voters.iter().filter_map(|voter_id| {
+            players
+                .get_player(*voter_id)
+                .filter(|player| player.object.is_some())
+                .and_then(|player| player.ip())
+        }))
+    }
+
+    fn required_votes(&self, kind: VoteKind) -> usize {
+        let threshold = match kind {
+            VoteKind::Kick(_) => self.config.vote.kick_threshold,
+            VoteKind::Mute(_) => self.config.vote.mute_threshold,
+            VoteKind::Game(GameVote::Reset) => self.config.vote.reset_threshold,
+            VoteKind::Game(GameVote::Start) => self.config.vote.start_threshold,
+        };
+        let ips_on_ice = count_distinct_ips(
+            self.state
+                .players
+                .players
+                .iter_players()
+                .filter(|(_, player)| player.object.is_some())
+                .filter_map(|(_, player)| player.ip()),
+        );
+        required_votes(ips_on_ice, threshold, self.config.vote.min_voters)
+    }
+
+    pub(crate) fn player_vote_target(&self, arg: &str) -> Option<PlayerId> {
+        let index = arg.parse::<PlayerIndex>().ok()?;
+        self.state
+            .players
+            .players
+            .get_player_by_index(index)
+            .map(|(player_id, _)| player_id)
+    }
+}
+
+fn count_distinct_ips(ips: impl Iterator<Item = IpAddr>) -> usize {
+    ips.collect::<HashSet<_>>().len()
+}
+
+/// Number of votes needed for a vote to pass, when `voters_on_ice` different IP addresses
+/// are on the ice.
+fn required_votes(voters_on_ice: usize, threshold: f32, min_voters: usize) -> usize {
+    ((voters_on_ice as f32 * threshold).ceil() as usize)
+        .max(min_voters)
+        .max(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vote::{count_distinct_ips, required_votes};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn same_ip_voters() {
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(count_distinct_ips([a, a, a].into_iter()), 1);
+        assert_eq!(count_distinct_ips([a, b, a].into_iter()), 2);
+        assert_eq!(count_distinct_ips(std::iter::empty()), 0);
+    }
+
+    #[test]
+    fn vote_threshold() {
+        assert_eq!(required_votes(10, 0.6, 3), 6);
+        assert_eq!(required_votes(9, 0.5, 3), 5);
+        // Few players on the ice, the minimum number of voters applies
+        assert_eq!(required_votes(4, 0.5, 3), 3);
+        // A vote never passes with a single vote
+        assert_eq!(required_votes(1, 0.5, 0), 2);
+        assert_eq!(required_votes(0, 0.0, 0), 2);
+    }
+}
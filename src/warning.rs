@@ -0,0 +1,260 @@
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::admin::AdminPermission;
+use crate::ban::BanInfo;
+use crate::game::{PlayerId, PlayerIndex};
+use crate::gamemode::{ExitReason, GameMode};
+use crate::server::{HQMServer, PlayerListExt};
+
+/// Automatic actions taken when a player has received enough warnings.
+///
+/// A value of 0 disables that step.
+#[derive(Debug, Clone)]
+pub struct WarningConfiguration {
+    pub mute_after: usize,
+    pub kick_after: usize,
+    pub ban_after: usize,
+    /// Length of the automatic mute, or [None] to mute until an administrator uses /unmute.
+    pub mute_duration: Option<Duration>,
+    pub ban_duration: Duration,
+}
+
+impl Default for WarningConfiguration {
+    fn default() -> Self {
+        Self {
+            mute_after: 2,
+            kick_after: 3,
+            ban_after: 4,
+            mute_duration: Some(Duration::from_secs(30 * 60)),
+            ban_duration: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// What happens to a player after a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WarningAction {
+    Mute,
+    Kick,
+    Ban,
+}
+
+impl WarningConfiguration {
+    /// Returns the harshest action reached with `count` warnings.
+    fn action(&self, count: usize) -> Option<WarningAction> {
+        let reached = |after: usize| after > 0 && count >= after;
+        if reached(self.ban_after) {
+            Some(WarningAction::Ban)
+        } else if reached(self.kick_after) {
+            Some(WarningAction::Kick)
+        } else if reached(self.mute_after) {
+            Some(WarningAction::Mute)
+        } else {
+            None
+        }
+    }
+}
+
+struct Warning {
+    player_name: Rc<str>,
+    ip: IpAddr,
+}
+
+#[derive(Default)]
+pub(crate) struct WarningState {
+    warnings: Vec<Warning>,
+}
+
+impl WarningState {
+    /// Counts the warnings given to a player name or IP address during this session.
+    pub(crate) fn count(&self, player_name: &str, ip: IpAddr) -> usize {
+        self.warnings
+            .iter()
+            .filter(|x| x.ip == ip || x.player_name.as_ref() == player_name)
+            .count()
+    }
+}
+
+impl HQMServer {
+    pub(crate) fn warn_player<B: GameMode>(
+        &mut self,
+        admin_player_id: PlayerId,
+        warn_player_index: PlayerIndex,
+        reason: &str,
+        behaviour: &mut B,
+    ) {
+        if let Some(admin_player) = self.state.players.players.check_permission_or_deny(
+            admin_player_id,
+            AdminPermission::Warn,
+            "warn",
+        ) {
+            let admin_player_name = admin_player.player_name.clone();
+
+            let Some((warn_player_id, warn_player)) = self
+                .state
+                .players
+                .players
+                .get_player_by_index(warn_player_index)
+            else {
+                return;
+            };
+            let Some(ip) = warn_player.ip() else {
+                return;
+            };
+            if warn_player_id == admin_player_id {
+                self.state
+                    .players
+                    .add_directed_server_chat_message("You cannot warn yourself", admin_player_id);
+                return;
+            }
+            let warn_player_name = warn_player.player_name.clone();
+
+            self.warnings.warnings.push(Warning {
+                player_name: warn_player_name.clone(),
+                ip,
+            });
+            let count = self.warnings.count(&warn_player_name, ip);
+            self.audit(
+                admin_player_id,
+                "warn",
+                reason,
+                Some(warn_player_id),
+                Ok(()),
+            );
+            info!(
+                "{} ({}) warned {} ({}), warning {}: {}",
+                admin_player_name, admin_player_id, warn_player_name, warn_player_id, count, reason
+            );
+            let msg = if reason.is_empty() {
+                format!(
+                    "{} warned by {} (warning {})",
+                    warn_player_name, admin_player_name, count
+                )
+            } else {
+                format!(
+                    "{} warned by {} (warning {}): {}",
+                    warn_player_name, admin_player_name, count, reason
+                )
+            };
+            self.state.players.add_server_chat_message(msg);
+
+            let config = &self.config.warnings;
+            match config.action(count) {
+                Some(WarningAction::Ban) => {
+                    // Banned through the ban check, so /bans and /unban see it
+                    let ban = BanInfo::new(
+                        None,
+                        &admin_player_name,
+                        &format!("{} warnings", count),
+                        Some(config.ban_duration),
+                    );
+                    info!(
+                        "{} ({}) banned for {} minutes after {} warnings",
+                        warn_player_name,
+                        warn_player_id,
+                        config.ban_duration.as_secs() / 60,
+                        count
+                    );
+                    // Audited before the player is removed, so the record still names them
+                    let args = format!(
+                        "{} warnings, {} minutes",
+                        count,
+                        config.ban_duration.as_secs() / 60
+                    );
+                    self.audit(admin_player_id, "ban", &args, Some(warn_player_id), Ok(()));
+                    self.kick_player_by(
+                        warn_player_id,
+                        Some(ban),
+                        &admin_player_name,
+                        ExitReason::AdminKicked,
+                        behaviour,
+                    );
+                }
+                Some(WarningAction::Kick) => {
+                    info!(
+                        "{} ({}) kicked after {} warnings",
+                        warn_player_name, warn_player_id, count
+                    );
+                    let args = format!("{} warnings", count);
+                    self.audit(admin_player_id, "kick", &args, Some(warn_player_id), Ok(()));
+                    self.kick_player_by(
+                        warn_player_id,
+                        None,
+                        &admin_player_name,
+                        ExitReason::AdminKicked,
+                        behaviour,
+                    );
+                }
+                Some(WarningAction::Mute) => {
+                    info!(
+                        "{} ({}) muted after {} warnings",
+                        warn_player_name, warn_player_id, count
+                    );
+                    let duration = config.mute_duration;
+                    let args = match duration {
+                        Some(duration) => {
+                            format!("{} warnings, {} minutes", count, duration.as_secs() / 60)
+                        }
+                        None => format!("{} warnings", count),
+                    };
+                    self.audit(admin_player_id, "mute", &args, Some(warn_player_id), Ok(()));
+                    self.mute_player_by(warn_player_id, &admin_player_name, duration);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warning::{Warning, WarningAction, WarningConfiguration, WarningState};
+    use std::net::IpAddr;
+
+    #[test]
+    fn warning_escalation() {
+        let config = WarningConfiguration::default();
+        assert_eq!(config.action(1), None);
+        assert_eq!(config.action(2), Some(WarningAction::Mute));
+        assert_eq!(config.action(3), Some(WarningAction::Kick));
+        assert_eq!(config.action(4), Some(WarningAction::Ban));
+        assert_eq!(config.action(10), Some(WarningAction::Ban));
+
+        // Disabled steps are skipped
+        let config = WarningConfiguration {
+            mute_after: 0,
+            kick_after: 2,
+            ban_after: 0,
+            ..Default::default()
+        };
+        assert_eq!(config.action(1), None);
+        assert_eq!(config.action(5), Some(WarningAction::Kick));
+    }
+
+    #[test]
+    fn warning_count() {
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        let mut state = WarningState::default();
+        state.warnings.push(Warning {
+            player_name: "Troll".into(),
+            ip: ip("10.0.0.1"),
+        });
+        state.warnings.push(Warning {
+            player_name: "Troll2".into(),
+            ip: ip("10.0.0.1"),
+        });
+        state.warnings.push(Warning {
+            player_name: "Troll".into(),
+            ip: ip("10.0.0.2"),
+        });
+        // Warnings follow both the name and the address
+        assert_eq!(state.count("Troll", ip("10.0.0.1")), 3);
+        assert_eq!(state.count("Troll2", ip("10.0.0.3")), 1);
+        assert_eq!(state.count("Other", ip("10.0.0.2")), 1);
+        assert_eq!(state.count("Other", ip("10.0.0.3")), 0);
+    }
+}